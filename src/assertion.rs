@@ -1,19 +1,43 @@
-pub fn is_true<T: AsRef<str>>(expression: T) -> bool {
-    use evalexpr::{eval, Value};
-    eval(expression.as_ref()) == Ok(Value::Boolean(true))
+use crate::report::{AssertionOutcome, ResponseSummary, Substitution, TestOutcome};
+use evalexpr::{
+    ContextWithMutableFunctions, EvalexprError, EvalexprResult, Function, HashMapContext, Value,
+};
+
+lazy_static::lazy_static! {
+    static ref CONTEXT: HashMapContext = functions::context();
+}
+
+/// evaluates to `Err(..)` when the expression is invalid or does not produce a boolean
+pub fn evaluate<T: AsRef<str>>(expression: T) -> Result<bool, String> {
+    match evalexpr::eval_with_context(expression.as_ref(), &*CONTEXT) {
+        Ok(Value::Boolean(b)) => Ok(b),
+        Ok(v) => Err(format!("expected a boolean, but got {}", v)),
+        Err(e) => Err(e.to_string()),
+    }
 }
 
 pub struct TestAssertions {
-    header: String,
+    request: String,
+    header: ResponseSummary,
     description: Option<String>,
-    assertions: String,
+    /// (<EXPRESSION>, <HYDRATED EXPRESSION>, <VARIABLES>)
+    assertions: Vec<(String, String, Vec<Substitution>)>,
+    /// assertions that were evaluated while the test was built, e.g. `[[tests.expect]]`
+    evaluated: Vec<AssertionOutcome>,
 }
 
-pub fn new(header: String, description: Option<String>, assertions: String) -> TestAssertions {
+pub fn new(
+    request: String,
+    header: ResponseSummary,
+    description: Option<String>,
+    assertions: Vec<(String, String, Vec<Substitution>)>,
+) -> TestAssertions {
     TestAssertions {
+        request,
         header,
         description,
         assertions,
+        evaluated: Vec::new(),
     }
 }
 
@@ -26,31 +50,181 @@ pub fn pretty_bool(result: bool) -> &'static str {
 }
 
 impl TestAssertions {
-    pub fn assert(&self, buffer: &mut String) -> bool {
-        let mut test = true;
-        let start = buffer.len();
+    pub fn add_evaluated<I: IntoIterator<Item = AssertionOutcome>>(&mut self, outcomes: I) {
+        self.evaluated.extend(outcomes);
+    }
 
-        buffer.push_str(&self.header);
+    pub fn assert(self) -> TestOutcome {
+        let assertions = self
+            .assertions
+            .into_iter()
+            .map(|(expression, hydrated, variables)| {
+                let result = self::evaluate(&hydrated);
+                AssertionOutcome {
+                    passed: result == Ok(true),
+                    error: result.err(),
+                    expression,
+                    hydrated,
+                    variables,
+                }
+            })
+            .chain(self.evaluated)
+            .collect();
 
-        if let Some(d) = &self.description {
-            buffer.push_str("\n📌 ");
-            buffer.push_str(d);
+        TestOutcome {
+            description: self.description,
+            request: Some(self.request),
+            response: Some(self.header),
+            assertions,
+            ..Default::default()
         }
+    }
+}
+
+/// functions available to every assertion line
+/// e.g. `matches({{ headers | content-type }}, "^application/json")`
+mod functions {
+    use super::*;
+
+    pub fn context() -> HashMapContext {
+        let mut context = HashMapContext::new();
 
-        buffer.push('\n');
+        let functions = [
+            ("matches", Function::new(matches)),
+            ("contains", Function::new(contains)),
+            ("starts_with", Function::new(starts_with)),
+            ("ends_with", Function::new(ends_with)),
+            ("len", Function::new(len)),
+            ("array", Function::new(array)),
+        ];
 
-        for t in self.assertions.lines() {
-            let assertion = t;
-            let test_result = self::is_true(assertion);
-            test &= test_result;
-            buffer.push_str("\n  ");
-            buffer.push_str(self::pretty_bool(test_result));
-            buffer.push_str(assertion);
+        for (name, function) in functions {
+            context
+                .set_function(name.to_string(), function)
+                .expect("HashMapContext supports functions");
         }
 
-        buffer.insert_str(start, self::pretty_bool(test));
-        buffer.insert(start, '\n');
+        context
+    }
+
+    /// numbers and booleans are matched against their text representation
+    fn text(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.to_string(),
+            _ => value.to_string(),
+        }
+    }
+
+    fn pair(argument: &Value) -> EvalexprResult<(Value, Value)> {
+        let mut tuple = argument.as_fixed_len_tuple(2)?.into_iter();
+        match (tuple.next(), tuple.next()) {
+            (Some(a), Some(b)) => Ok((a, b)),
+            _ => unreachable!("tuple has a fixed length of 2"),
+        }
+    }
+
+    fn matches(argument: &Value) -> EvalexprResult<Value> {
+        let (value, pattern) = pair(argument)?;
+        let regex = regex::Regex::new(&pattern.as_string()?)
+            .map_err(|e| EvalexprError::CustomMessage(e.to_string()))?;
+        Ok(Value::Boolean(regex.is_match(&text(&value))))
+    }
+
+    fn contains(argument: &Value) -> EvalexprResult<Value> {
+        let (haystack, needle) = pair(argument)?;
+        Ok(Value::Boolean(match haystack {
+            Value::Tuple(items) => items.contains(&needle),
+            _ => text(&haystack).contains(&text(&needle)),
+        }))
+    }
+
+    fn starts_with(argument: &Value) -> EvalexprResult<Value> {
+        let (value, prefix) = pair(argument)?;
+        Ok(Value::Boolean(text(&value).starts_with(&text(&prefix))))
+    }
+
+    fn ends_with(argument: &Value) -> EvalexprResult<Value> {
+        let (value, suffix) = pair(argument)?;
+        Ok(Value::Boolean(text(&value).ends_with(&text(&suffix))))
+    }
+
+    /// a tuple with the argument as its one item, or no items for `array()`
+    ///
+    /// several arguments arrive as one tuple, so `array("a", "b")` is `(("a", "b"))`
+    fn array(argument: &Value) -> EvalexprResult<Value> {
+        Ok(Value::Tuple(match argument {
+            Value::Empty => Vec::new(),
+            _ => vec![argument.clone()],
+        }))
+    }
+
+    fn len(argument: &Value) -> EvalexprResult<Value> {
+        let len = match argument {
+            Value::String(s) => s.chars().count(),
+            Value::Tuple(t) => t.len(),
+            _ => return Err(EvalexprError::expected_string(argument.clone())),
+        };
+        Ok(Value::Int(len as i64))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluate_reports_errors_instead_of_false() {
+        assert_eq!(evaluate("200 == 200"), Ok(true));
+        assert_eq!(evaluate(r#""2" == 2"#), Ok(false));
+        assert!(evaluate(r#""hello" > 2"#).is_err());
+        assert!(evaluate("1 + 1").is_err());
+        assert!(evaluate("{{ missing }} == 2").is_err());
+    }
+
+    #[test]
+    fn string_functions() {
+        assert_eq!(
+            evaluate(r#"matches("application/json; charset=utf-8", "^application/json")"#),
+            Ok(true)
+        );
+        assert_eq!(evaluate(r#"matches(201, "^2[0-9]{2}$")"#), Ok(true));
+        assert_eq!(evaluate(r#"contains("hello world", "world")"#), Ok(true));
+        assert_eq!(evaluate(r#"contains(("a", "b"), "c")"#), Ok(false));
+        assert_eq!(evaluate(r#"starts_with("hello world", "hello")"#), Ok(true));
+        assert_eq!(evaluate(r#"ends_with("hello world", "hello")"#), Ok(false));
+        assert_eq!(evaluate(r#"len("hello") == 5"#), Ok(true));
+        assert_eq!(evaluate(r#"len(("a", "b")) == 2"#), Ok(true));
+        assert!(evaluate(r#"matches("hello", "(")"#).is_err());
+        assert!(evaluate(r#"contains("hello")"#).is_err());
+    }
+
+    #[test]
+    fn json_arrays_are_tuples() {
+        use crate::{query::Content, store::Store};
+
+        let mut map = crate::store::ContentMap::new();
+        map.insert(
+            "body".to_string(),
+            Content::new(
+                r#"{ "items": [1, 2, 3], "tags": ["a"], "none": [], "nested": [["a", "b"]] }"#
+                    .to_string(),
+            ),
+        );
+        let store = crate::store::StoreUnion::MapStringToContent(map);
+        let hydrate = |a: &str| evaluate(store.match_and_replace(a, |v| v.as_literal()));
 
-        test
+        assert_eq!(hydrate("len({{ body | items }}) == 3"), Ok(true));
+        assert_eq!(hydrate("contains({{ body | items }}, 2)"), Ok(true));
+        assert_eq!(hydrate(r#"{{ body | tags }} == array("a")"#), Ok(true));
+        assert_eq!(hydrate(r#"contains({{ body | tags }}, "a")"#), Ok(true));
+        assert_eq!(hydrate("len({{ body | tags }}) == 1"), Ok(true));
+        assert_eq!(hydrate("len({{ body | none }}) == 0"), Ok(true));
+        assert_eq!(hydrate("len({{ body | nested }}) == 1"), Ok(true));
+        assert_eq!(
+            hydrate(r#"{{ body | nested }} == array(("a", "b"))"#),
+            Ok(true)
+        );
+        assert_eq!(hydrate(r#"len(array("a", "b")) == 1"#), Ok(true));
+        assert_eq!(hydrate(r#"array("a", "b") == array(("a", "b"))"#), Ok(true));
     }
 }
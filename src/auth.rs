@@ -0,0 +1,354 @@
+use crate::{oauth2::OAuth2Config, store::Store};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// credentials sent with a request, from `[auth]` or a test's `[tests.auth]`
+///
+/// ```toml
+/// [auth]
+/// type = "basic"
+/// username = "{{ username }}"
+/// password = "{{ password }}"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthConfig {
+    /// `Authorization: Basic base64(<username>:<password>)`
+    Basic { username: String, password: String },
+    /// `Authorization: Bearer <token>`
+    Bearer { token: String },
+    /// answers the `WWW-Authenticate: Digest` challenge of a 401 response, then sends the request again
+    Digest { username: String, password: String },
+    /// `Authorization: Bearer <token>` with a token from the client credentials grant
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuth2Config),
+}
+
+impl AuthConfig {
+    /// replaces the `{{ variables }}` in every credential
+    pub fn hydrate<S: Store>(&self, store: &S) -> AuthConfig {
+        let hydrate = |s: &String| store.match_and_replace(s, |v| v.as_value());
+
+        match self {
+            AuthConfig::Basic { username, password } => AuthConfig::Basic {
+                username: hydrate(username),
+                password: hydrate(password),
+            },
+            AuthConfig::Bearer { token } => AuthConfig::Bearer {
+                token: hydrate(token),
+            },
+            AuthConfig::Digest { username, password } => AuthConfig::Digest {
+                username: hydrate(username),
+                password: hydrate(password),
+            },
+            AuthConfig::OAuth2(oauth2) => AuthConfig::OAuth2(oauth2.hydrate(store)),
+        }
+    }
+
+    /// every credential, used to find the variables a test reads
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            AuthConfig::Basic { username, password }
+            | AuthConfig::Digest { username, password } => {
+                vec![username, password]
+            }
+            AuthConfig::Bearer { token } => vec![token],
+            AuthConfig::OAuth2(oauth2) => oauth2.values(),
+        }
+    }
+
+    /// `Authorization` header value, digest needs a challenge and oauth2 a token first so they have none
+    pub fn header(&self) -> Option<String> {
+        match self {
+            AuthConfig::Basic { username, password } => Some(self::basic(username, password)),
+            AuthConfig::Bearer { token } => Some(format!("Bearer {}", token)),
+            AuthConfig::Digest { .. } | AuthConfig::OAuth2(_) => None,
+        }
+    }
+}
+
+/// `[auth]` with a `type`, or an `[auth.oauth2]` table
+///
+/// ```toml
+/// [auth.oauth2]
+/// token_url = "https://auth.example.com/oauth/token"
+/// client_id = "{{ clientId }}"
+/// client_secret = "{{ clientSecret }}"
+/// ```
+pub fn table<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<AuthConfig>, D::Error> {
+    let Some(table) = Option::<toml::Table>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    let config = match table.get("oauth2") {
+        Some(oauth2) if table.len() == 1 => oauth2.clone().try_into().map(AuthConfig::OAuth2),
+        _ => toml::Value::Table(table).try_into(),
+    };
+    config.map(Some).map_err(serde::de::Error::custom)
+}
+
+/// `WWW-Authenticate: Digest ...` from a 401 response, RFC 7616
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: String,
+    /// true when the server supports `qop=auth`
+    qop: bool,
+}
+
+impl Challenge {
+    pub fn parse(header: &str) -> Option<Challenge> {
+        let (scheme, params) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+
+        let params = self::params(params);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.to_string())
+        };
+
+        Some(Challenge {
+            realm: param("realm")?,
+            nonce: param("nonce")?,
+            opaque: param("opaque"),
+            algorithm: param("algorithm").unwrap_or_else(|| "MD5".to_string()),
+            qop: param("qop").is_some_and(|q| q.split(',').any(|q| q.trim() == "auth")),
+        })
+    }
+
+    /// `Authorization` header value for `method uri`, `None` when the algorithm is not supported
+    pub fn authorization(
+        &self,
+        username: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        cnonce: &str,
+    ) -> Option<String> {
+        let (hash, session): (fn(&str) -> String, bool) =
+            match self.algorithm.to_uppercase().as_str() {
+                "MD5" => (md5, false),
+                "MD5-SESS" => (md5, true),
+                "SHA-256" => (sha256, false),
+                "SHA-256-SESS" => (sha256, true),
+                _ => return None,
+            };
+        let nc = "00000001";
+
+        let mut ha1 = hash(&format!("{}:{}:{}", username, self.realm, password));
+        if session {
+            ha1 = hash(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+        let ha2 = hash(&format!("{}:{}", method, uri));
+
+        let response = if self.qop {
+            hash(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, cnonce, ha2
+            ))
+        } else {
+            hash(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+
+        let mut header = format!(
+            r#"Digest username="{}", realm="{}", nonce="{}", uri="{}", algorithm={}, response="{}""#,
+            username, self.realm, self.nonce, uri, self.algorithm, response
+        );
+        if self.qop {
+            header.push_str(&format!(r#", qop=auth, nc={}, cnonce="{}""#, nc, cnonce));
+        }
+        if let Some(opaque) = &self.opaque {
+            header.push_str(&format!(r#", opaque="{}""#, opaque));
+        }
+
+        Some(header)
+    }
+}
+
+/// a client nonce that differs between requests
+pub fn cnonce() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    md5(&format!("{}:{}", now.as_nanos(), std::process::id()))[..16].to_string()
+}
+
+/// `key=value` and `key="quoted, value"` pairs separated by commas
+fn params(input: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut rest = input.trim();
+
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let after = after.trim_start();
+
+        let (value, next) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => after.split_once(',').unwrap_or((after, "")),
+        };
+
+        params.push((key, value.trim().to_string()));
+        rest = next.trim_start().trim_start_matches(',');
+    }
+
+    params
+}
+
+fn md5(input: &str) -> String {
+    use md5::Digest;
+    self::hex(&md5::Md5::digest(input.as_bytes()))
+}
+
+fn sha256(input: &str) -> String {
+    use sha2::Digest;
+    self::hex(&sha2::Sha256::digest(input.as_bytes()))
+}
+
+/// `Basic base64(<username>:<password>)`, also used for proxy and oauth2 client credentials
+pub fn basic(username: &str, password: &str) -> String {
+    use base64::Engine;
+    let credentials =
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+    format!("Basic {}", credentials)
+}
+
+/// lowercase hex of `bytes`, how digests and signatures are written
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        client::{Client, ClientConfig},
+        http_file::RequestBuilder,
+        test::server,
+    };
+
+    #[test]
+    fn basic_and_bearer_headers() {
+        let basic: AuthConfig =
+            toml::from_str("type = \"basic\"\nusername = \"user\"\npassword = \"secret\"").unwrap();
+        assert_eq!(basic.header().as_deref(), Some("Basic dXNlcjpzZWNyZXQ="));
+
+        let bearer: AuthConfig = toml::from_str("type = \"bearer\"\ntoken = \"abc\"").unwrap();
+        assert_eq!(bearer.header().as_deref(), Some("Bearer abc"));
+    }
+
+    #[test]
+    fn oauth2_table_and_type_are_the_same() {
+        let table: crate::config::Config = toml::from_str(
+            r#"
+environment = {}
+tests = []
+
+[auth.oauth2]
+token_url = "https://auth.example.com/oauth/token"
+client_id = "hat"
+client_secret = "secret"
+scopes = ["users:read"]
+"#,
+        )
+        .unwrap();
+        let typed: crate::config::Config = toml::from_str(
+            r#"
+environment = {}
+tests = []
+
+[auth]
+type = "oauth2"
+token_url = "https://auth.example.com/oauth/token"
+client_id = "hat"
+client_secret = "secret"
+scopes = ["users:read"]
+"#,
+        )
+        .unwrap();
+
+        assert!(matches!(table.auth, Some(AuthConfig::OAuth2(_))));
+        assert_eq!(table.auth, typed.auth);
+
+        let missing = toml::from_str::<crate::config::Config>(
+            "environment = {}\ntests = []\n[auth.oauth2]\ntoken_url = \"https://auth.example.com\"",
+        );
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn digest_matches_the_rfc_examples() {
+        // RFC 2617 section 3.5
+        let challenge = Challenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )
+        .unwrap();
+        let header = challenge
+            .authorization(
+                "Mufasa",
+                "Circle Of Life",
+                "GET",
+                "/dir/index.html",
+                "0a4f113b",
+            )
+            .unwrap();
+        assert!(header.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(header.contains(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+
+        // RFC 7616 section 3.9.1
+        let challenge = Challenge::parse(
+            r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=SHA-256, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#,
+        )
+        .unwrap();
+        let header = challenge
+            .authorization(
+                "Mufasa",
+                "Circle of Life",
+                "GET",
+                "/dir/index.html",
+                "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+            )
+            .unwrap();
+        assert!(header.contains(
+            r#"response="753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1""#
+        ));
+    }
+
+    #[test]
+    fn digest_answers_the_challenge() {
+        // challenges the first request and accepts the second
+        let (port, server) = server::serve(vec![
+            server::response(
+                "401 Unauthorized",
+                &[r#"WWW-Authenticate: Digest realm="hat", nonce="abc", qop="auth""#],
+                "",
+            ),
+            server::response("200 OK", &[], "ok"),
+        ]);
+
+        let client = Client::new(ClientConfig::default());
+        let request =
+            RequestBuilder::new("GET", &format!("http://127.0.0.1:{}/me?full=true", port))
+                .unwrap()
+                .auth(AuthConfig::Digest {
+                    username: "user".to_string(),
+                    password: "secret".to_string(),
+                });
+        let response = client.request(request, None).unwrap().send().unwrap();
+        assert_eq!(response.into_string().unwrap(), "ok");
+
+        let requests = server.join().unwrap();
+        let authorization = requests[1].header("authorization").unwrap();
+        assert!(authorization.starts_with(
+            r#"Digest username="user", realm="hat", nonce="abc", uri="/me?full=true""#
+        ));
+        assert!(authorization.contains("qop=auth, nc=00000001"));
+    }
+}
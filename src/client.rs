@@ -0,0 +1,550 @@
+use crate::{
+    auth::{AuthConfig, Challenge},
+    cookies::CookieJar,
+    error::HatError,
+    http_file::{Body, RequestBuilder},
+    oauth2::TokenCache,
+    proxy::{Proxy, ProxyConfig},
+    tls::{ClientCertificate, TlsConfig},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+/// settings for the http client, from `[client]` or a test's `[tests.client]`
+///
+/// ```toml
+/// [client]
+/// connect_timeout = "2s"
+/// timeout = "10s"
+/// redirects = 0
+/// user_agent = "hat"
+/// keep_alive = false
+/// headers = { accept = "application/json" }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// time allowed to open a connection
+    #[serde(default, with = "humantime_serde")]
+    pub connect_timeout: Option<Duration>,
+    /// time allowed for each read from the socket
+    #[serde(default, with = "humantime_serde")]
+    pub read_timeout: Option<Duration>,
+    /// time allowed for each write to the socket
+    #[serde(default, with = "humantime_serde")]
+    pub write_timeout: Option<Duration>,
+    /// time allowed for the whole request, including redirects
+    #[serde(default, with = "humantime_serde")]
+    pub timeout: Option<Duration>,
+    /// redirects to follow, 0 returns the redirect response itself
+    pub redirects: Option<u32>,
+    pub user_agent: Option<String>,
+    /// false opens a new connection for every request
+    pub keep_alive: Option<bool>,
+    /// headers sent with every request unless the request sets them
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// certificate authorities, client certificates, and `insecure`
+    pub tls: Option<TlsConfig>,
+    /// `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY`, and `NO_PROXY` are used when missing
+    pub proxy: Option<ProxyConfig>,
+    /// keep the cookies of every response and send them with later requests
+    pub cookies: Option<bool>,
+}
+
+impl ClientConfig {
+    /// `overrides` takes precedence over `self`, headers are merged
+    pub fn merge(&self, overrides: &ClientConfig) -> ClientConfig {
+        let mut headers = self.headers.clone();
+        headers.extend(overrides.headers.clone());
+
+        ClientConfig {
+            connect_timeout: overrides.connect_timeout.or(self.connect_timeout),
+            read_timeout: overrides.read_timeout.or(self.read_timeout),
+            write_timeout: overrides.write_timeout.or(self.write_timeout),
+            timeout: overrides.timeout.or(self.timeout),
+            redirects: overrides.redirects.or(self.redirects),
+            user_agent: overrides
+                .user_agent
+                .clone()
+                .or_else(|| self.user_agent.clone()),
+            keep_alive: overrides.keep_alive.or(self.keep_alive),
+            headers,
+            tls: overrides.tls.clone().or_else(|| self.tls.clone()),
+            proxy: overrides.proxy.clone().or_else(|| self.proxy.clone()),
+            cookies: overrides.cookies.or(self.cookies),
+        }
+    }
+
+    fn agent(&self, destination: &Destination) -> Result<ureq::Agent, HatError> {
+        let mut builder = ureq::AgentBuilder::new();
+
+        if let Some(tls) = &self.tls {
+            builder = builder.tls_config(tls.client_config(destination.certificate.as_ref())?);
+        }
+        if let Some(proxy) = &destination.proxy {
+            let proxy = ureq::Proxy::new(&proxy.url).map_err(|e| HatError::Proxy(e.to_string()))?;
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(t) = self.connect_timeout {
+            builder = builder.timeout_connect(t);
+        }
+        if let Some(t) = self.read_timeout {
+            builder = builder.timeout_read(t);
+        }
+        if let Some(t) = self.write_timeout {
+            builder = builder.timeout_write(t);
+        }
+        if let Some(t) = self.timeout {
+            builder = builder.timeout(t);
+        }
+        if let Some(r) = self.redirects {
+            builder = builder.redirects(r);
+        }
+        if let Some(u) = &self.user_agent {
+            builder = builder.user_agent(u);
+        }
+        if self.keep_alive == Some(false) {
+            builder = builder.max_idle_connections(0);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// settings that depend on the host being requested
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Destination {
+    certificate: Option<ClientCertificate>,
+    proxy: Option<Proxy>,
+}
+
+impl Destination {
+    fn new(config: &ClientConfig, url: &url::Url) -> Self {
+        let host = url.host_str().unwrap_or_default();
+
+        Self {
+            certificate: config
+                .tls
+                .as_ref()
+                .and_then(|t| t.certificate(host))
+                .cloned(),
+            proxy: crate::proxy::resolve(config.proxy.as_ref(), url.scheme(), host, |name| {
+                std::env::var(name).ok()
+            }),
+        }
+    }
+}
+
+/// the `redirects` of a ureq agent that does not set it
+const DEFAULT_REDIRECTS: u32 = 5;
+
+/// builds requests with an agent for each distinct client config, so connections are reused
+pub struct Client {
+    config: ClientConfig,
+    agents: Mutex<Vec<(ClientConfig, Destination, ureq::Agent)>>,
+    cookies: CookieJar,
+    tokens: TokenCache,
+}
+
+impl Client {
+    pub fn new(config: ClientConfig) -> Self {
+        Self {
+            config,
+            agents: Mutex::new(Vec::new()),
+            cookies: CookieJar::default(),
+            tokens: TokenCache::default(),
+        }
+    }
+
+    pub fn cookies(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    /// whether the cookie jar is enabled for a test with `overrides`
+    pub fn cookies_enabled(&self, overrides: Option<&ClientConfig>) -> bool {
+        overrides
+            .and_then(|o| o.cookies)
+            .or(self.config.cookies)
+            .unwrap_or_default()
+    }
+
+    /// keeps the cookies of `response` when the cookie jar is enabled
+    pub fn receive(&self, response: &ureq::Response, overrides: Option<&ClientConfig>) {
+        let enabled = self.cookies_enabled(overrides);
+
+        if let (true, Ok(url)) = (enabled, url::Url::parse(response.get_url())) {
+            self.cookies.store(&url, response.all("set-cookie"));
+        }
+    }
+
+    /// sends the request, redirects are followed here when the cookie jar is enabled so the
+    /// cookies of every response along the way are kept, ureq would drop them
+    pub fn send(&self, mut request: PreparedRequest) -> Result<ureq::Response, HatError> {
+        let limit = request.redirects;
+        let url = url::Url::parse(request.request.url()).map_err(|_| HatError::RequestBuilder)?;
+        let mut followed = 0;
+
+        loop {
+            let response = request.send()?;
+
+            let Some(limit @ 1..) = limit else {
+                return Ok(response);
+            };
+            if !(300..=399).contains(&response.status()) {
+                return Ok(response);
+            }
+            // the same limit and error as ureq, which stops at the redirect that reaches it
+            if followed + 1 >= limit {
+                return Err(HatError::HttpResponse(format!(
+                    "{}: Too Many Redirects: reached max redirects ({})",
+                    url, limit
+                )));
+            }
+
+            let location = match response.status() {
+                301 | 302 | 303 | 307 | 308 => response
+                    .header("location")
+                    .and_then(|l| url::Url::parse(response.get_url()).ok()?.join(l).ok()),
+                _ => None,
+            };
+            let Some(location) = location else {
+                return Ok(response);
+            };
+
+            if let Ok(url) = url::Url::parse(response.get_url()) {
+                self.cookies.store(&url, response.all("set-cookie"));
+            }
+            request = self.redirect(request, response.status(), location)?;
+            followed += 1;
+        }
+    }
+
+    /// the request for the `Location` of a redirect, a 301, 302, or 303 becomes a GET without a body
+    fn redirect(
+        &self,
+        previous: PreparedRequest,
+        status: u16,
+        url: url::Url,
+    ) -> Result<PreparedRequest, HatError> {
+        let (method, body) = match (status, previous.request.method()) {
+            (307 | 308, method) => (method.to_string(), previous.body),
+            (_, "HEAD") => ("HEAD".to_string(), None),
+            _ => ("GET".to_string(), None),
+        };
+        let same_host = url::Url::parse(previous.request.url()).is_ok_and(|p| {
+            p.host_str() == url.host_str()
+                && p.port_or_known_default() == url.port_or_known_default()
+        });
+
+        let destination = Destination::new(&previous.config, &url);
+        let agent = self.agent(&previous.config, &destination)?;
+        let mut request = agent.request_url(&method, &url);
+
+        for name in previous.request.header_names() {
+            let keep = match name.to_ascii_lowercase().as_str() {
+                // the jar has the cookies of the redirect by now
+                "cookie" | "proxy-authorization" => false,
+                "authorization" => same_host,
+                "content-type" | "content-length" => body.is_some(),
+                _ => true,
+            };
+            if let (true, Some(value)) = (keep, previous.request.header(&name)) {
+                request = request.set(&name, value);
+            }
+        }
+
+        if let Some(cookies) = self.cookies.header(&url) {
+            request = request.set("Cookie", &cookies);
+        }
+        if let Some(authorization) = self::proxy_authorization(&destination, &url) {
+            request = request.set("Proxy-Authorization", authorization);
+        }
+
+        Ok(PreparedRequest {
+            request,
+            body,
+            ..previous
+        })
+    }
+
+    /// `overrides` are the settings of a single test, the request body is returned as is
+    pub fn request(
+        &self,
+        request: RequestBuilder,
+        overrides: Option<&ClientConfig>,
+    ) -> Result<PreparedRequest, HatError> {
+        let config = match overrides {
+            Some(o) => self.config.merge(o),
+            None => self.config.clone(),
+        };
+
+        let url = url::Url::parse(request.get_url()).map_err(|_| HatError::RequestBuilder)?;
+        let destination = Destination::new(&config, &url);
+
+        // with the cookie jar, redirects are followed by `send` instead of ureq
+        let redirects =
+            (config.cookies == Some(true)).then(|| config.redirects.unwrap_or(DEFAULT_REDIRECTS));
+        let config = match redirects {
+            Some(_) => ClientConfig {
+                redirects: Some(0),
+                ..config
+            },
+            None => config,
+        };
+
+        let agent = self.agent(&config, &destination)?;
+        let auth = request.get_auth().cloned();
+        let sign = request.get_sign().cloned();
+        let (builder, endpoint, body) = request.split();
+        let mut request =
+            RequestBuilder::build(builder, endpoint, &agent).ok_or(HatError::RequestBuilder)?;
+
+        for (name, value) in &config.headers {
+            if !request.has(name) {
+                request = request.set(name, value);
+            }
+        }
+
+        // a cookie header written in the request replaces the jar
+        if config.cookies == Some(true) && !request.has("cookie") {
+            if let Some(cookies) = self.cookies.header(&url) {
+                request = request.set("Cookie", &cookies);
+            }
+        }
+
+        if let Some(AuthConfig::OAuth2(oauth2)) = &auth {
+            let token_url = url::Url::parse(&oauth2.token_url)
+                .map_err(|e| HatError::Auth(format!("{}: {}", oauth2.token_url, e)))?;
+            let agent = self.agent(&config, &Destination::new(&config, &token_url))?;
+            let token = self.tokens.token(oauth2, &agent)?;
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+
+        // signed last, so the signature covers every header above
+        if let Some(sign) = &sign {
+            let bytes = match &body {
+                Some(body) => body.bytes().map_err(|e| HatError::Signing(e.to_string()))?,
+                None => Default::default(),
+            };
+            request = sign.sign(request, &bytes)?;
+        }
+
+        if let Some(authorization) = self::proxy_authorization(&destination, &url) {
+            request = request.set("Proxy-Authorization", authorization);
+        }
+
+        Ok(PreparedRequest {
+            request,
+            body,
+            auth,
+            config,
+            redirects,
+        })
+    }
+
+    fn agent(
+        &self,
+        config: &ClientConfig,
+        destination: &Destination,
+    ) -> Result<ureq::Agent, HatError> {
+        let mut agents = self.agents.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some((.., agent)) = agents
+            .iter()
+            .find(|(c, d, _)| c == config && d == destination)
+        {
+            return Ok(agent.clone());
+        }
+
+        let agent = config.agent(destination)?;
+        agents.push((config.clone(), destination.clone(), agent.clone()));
+        Ok(agent)
+    }
+}
+
+/// a request ready to be sent, the body and digest credentials are kept to answer a challenge
+pub struct PreparedRequest {
+    pub request: ureq::Request,
+    pub body: Option<Body>,
+    auth: Option<AuthConfig>,
+    /// settings the request was built with, a redirect is built with them as well
+    config: ClientConfig,
+    /// the `redirects` setting when `Client::send` follows them, `None` when ureq does
+    redirects: Option<u32>,
+}
+
+impl PreparedRequest {
+    /// sends the request, a digest challenge is answered by sending it again
+    pub fn send(&self) -> Result<ureq::Response, HatError> {
+        let body = self.body.as_ref();
+        let response = self::call(self.request.clone(), body);
+
+        let challenge = match (&self.auth, &response) {
+            (Some(AuthConfig::Digest { .. }), Err(e)) => match e.as_ref() {
+                ureq::Error::Status(401, r) => r
+                    .all("www-authenticate")
+                    .into_iter()
+                    .find_map(Challenge::parse),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let response = match (&self.auth, challenge) {
+            (Some(AuthConfig::Digest { username, password }), Some(challenge)) => {
+                match challenge.authorization(
+                    username,
+                    password,
+                    self.request.method(),
+                    &self::digest_uri(self.request.url()),
+                    &crate::auth::cnonce(),
+                ) {
+                    Some(authorization) => self::call(
+                        self.request.clone().set("Authorization", &authorization),
+                        body,
+                    ),
+                    None => response,
+                }
+            }
+            _ => response,
+        };
+
+        response.map_err(|e| HatError::HttpResponse(e.to_string()))
+    }
+}
+
+/// https is tunneled with CONNECT, which ureq authenticates itself
+fn proxy_authorization<'a>(destination: &'a Destination, url: &url::Url) -> Option<&'a str> {
+    let proxy = destination.proxy.as_ref()?;
+    (url.scheme() == "http")
+        .then_some(proxy.authorization.as_deref())
+        .flatten()
+}
+
+fn call(request: ureq::Request, body: Option<&Body>) -> Result<ureq::Response, Box<ureq::Error>> {
+    match body {
+        Some(body) => body.send(request),
+        None => request.call().map_err(Box::new),
+    }
+}
+
+/// path and query of `url`, the `uri` a digest response is computed for
+fn digest_uri(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(url) => match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let client: ClientConfig = toml::from_str(
+            "timeout = \"10s\"\nredirects = 5\nheaders = { accept = \"application/json\", x-env = \"ci\" }",
+        )
+        .unwrap();
+        let test: ClientConfig =
+            toml::from_str("redirects = 0\nheaders = { accept = \"text/plain\" }").unwrap();
+
+        let merged = client.merge(&test);
+        assert_eq!(merged.timeout, Some(Duration::from_secs(10)));
+        assert_eq!(merged.redirects, Some(0));
+        assert_eq!(merged.headers["accept"], "text/plain");
+        assert_eq!(merged.headers["x-env"], "ci");
+    }
+
+    #[test]
+    fn default_headers_do_not_replace_request_headers() {
+        let client = Client::new(ClientConfig {
+            headers: BTreeMap::from([
+                ("accept".to_string(), "application/json".to_string()),
+                ("x-env".to_string(), "ci".to_string()),
+            ]),
+            ..Default::default()
+        });
+
+        let request = RequestBuilder::new("GET", "http://localhost:5000")
+            .unwrap()
+            .add_header("accept", "text/plain");
+        let request = client.request(request, None).unwrap().request;
+
+        assert_eq!(request.header("accept"), Some("text/plain"));
+        assert_eq!(request.header("x-env"), Some("ci"));
+    }
+
+    #[test]
+    fn cookies_of_a_redirect_are_kept() {
+        use crate::test::server;
+
+        // a login that sets the session on a redirect
+        let (port, server) = server::serve(vec![
+            server::response(
+                "302 Found",
+                &["Location: /me", "Set-Cookie: session=abc; Path=/"],
+                "",
+            ),
+            server::response("200 OK", &[], "ok"),
+        ]);
+
+        let client = Client::new(ClientConfig {
+            cookies: Some(true),
+            ..Default::default()
+        });
+        let request =
+            RequestBuilder::new("POST", &format!("http://127.0.0.1:{}/login", port)).unwrap();
+        let response = client.send(client.request(request, None).unwrap()).unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.into_string().unwrap(), "ok");
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[1].head[0], "GET /me HTTP/1.1");
+        assert_eq!(requests[1].header("cookie"), Some("session=abc"));
+
+        let url = url::Url::parse(&format!("http://127.0.0.1:{}/", port)).unwrap();
+        assert_eq!(
+            client.cookies().header(&url).as_deref(),
+            Some("session=abc")
+        );
+    }
+
+    #[test]
+    fn redirect_limit_is_the_same_with_the_cookie_jar() {
+        use crate::test::server;
+
+        for cookies in [None, Some(true)] {
+            let (port, server) = server::serve(vec![
+                server::response("302 Found", &["Location: /a"], ""),
+                server::response("302 Found", &["Location: /b"], ""),
+            ]);
+
+            let client = Client::new(ClientConfig {
+                redirects: Some(2),
+                cookies,
+                ..Default::default()
+            });
+            let url = format!("http://127.0.0.1:{}/", port);
+            let request = RequestBuilder::new("GET", &url).unwrap();
+            let error = client
+                .send(client.request(request, None).unwrap())
+                .unwrap_err();
+
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "response failed: {}: Too Many Redirects: reached max redirects (2)",
+                    url
+                ),
+                "cookies = {:?}",
+                cookies
+            );
+            assert_eq!(server.join().unwrap().len(), 2);
+        }
+    }
+}
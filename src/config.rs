@@ -1,13 +1,23 @@
 use crate::{
     assertion,
+    auth::AuthConfig,
+    client::ClientConfig,
+    cookies::CookiesConfig,
     error::HatError,
     factory,
-    runner::{HatTestBuilder, HatTestOutput, RequestExecutor},
+    http_file::{Body, MultipartConfig},
+    operator::Expectation,
+    poll::PollConfig,
+    report::{ResponseSummary, Substitution},
+    retry::RetryConfig,
+    runner::{HatTestBuilder, HatTestOutput, RequestExecutor, RunOptions},
+    signing::SigningConfig,
+    snapshot::SnapshotConfig,
     store::Store,
 };
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, time::Instant};
 
 pub fn read<P: AsRef<Path>>(path: P) -> anyhow::Result<Config> {
     let buffer = std::fs::read_to_string(path.as_ref()).with_context(|| {
@@ -36,24 +46,137 @@ pub fn read<P: AsRef<Path>>(path: P) -> anyhow::Result<Config> {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub environment: HashMap<String, String>,
+    /// settings for the http client used by every test
+    #[serde(default)]
+    pub client: ClientConfig,
+    /// credentials for every test that does not set its own `auth`
+    #[serde(default, deserialize_with = "crate::auth::table")]
+    pub auth: Option<AuthConfig>,
+    /// request signing for every test that does not set its own `sign`
+    pub sign: Option<SigningConfig>,
     pub tests: Vec<TestConfig>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TestConfig {
     description: Option<String>,
+    /// name other tests can use in `depends_on`
+    id: Option<String>,
+    /// ids of earlier tests that must finish first, in addition to the tests whose outputs are used
+    #[serde(default)]
+    depends_on: Vec<String>,
     http: String,
+    #[serde(default)]
     assertions: String,
+    expect: Option<Vec<Expectation>>,
+    /// path to a JSON Schema file the response body must conform to
+    schema: Option<String>,
+    /// path to a file the normalized response is compared against
+    snapshot: Option<SnapshotConfig>,
+    /// sends the request again until the assertions pass or the attempts run out
+    retry: Option<RetryConfig>,
+    /// overrides the `[client]` settings for this test
+    client: Option<ClientConfig>,
+    /// clears or sets cookies in the cookie jar before the request is sent
+    cookies: Option<CookiesConfig>,
+    /// replaces the `[auth]` credentials for this test
+    #[serde(default, deserialize_with = "crate::auth::table")]
+    auth: Option<AuthConfig>,
+    /// replaces the `[sign]` settings for this test
+    sign: Option<SigningConfig>,
+    /// sends the fields and files as a `multipart/form-data` body
+    multipart: Option<MultipartConfig>,
+    /// sends the request again until a condition holds, the assertions use the last response
+    poll: Option<PollConfig>,
     outputs: Option<HashMap<String, String>>,
 }
 
+impl TestConfig {
+    /// a test without `auth` uses the `[auth]` of its config
+    pub fn or_auth(mut self, auth: Option<&AuthConfig>) -> Self {
+        self.auth = self.auth.or_else(|| auth.cloned());
+        self
+    }
+
+    /// a test without `sign` uses the `[sign]` of its config
+    pub fn or_sign(mut self, sign: Option<&SigningConfig>) -> Self {
+        self.sign = self.sign.or_else(|| sign.cloned());
+        self
+    }
+}
+
 impl HatTestBuilder for TestConfig {
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn depends_on(&self) -> &[String] {
+        &self.depends_on
+    }
+
+    fn variables(&self) -> Vec<String> {
+        // a missing .http file is reported when the test is built
+        let http = crate::http_file::get_contents(self.http.to_string()).unwrap_or_default();
+        let file_variables = crate::http_file::file_variables(&self.http).unwrap_or_default();
+        let includes: Vec<String> =
+            crate::http_file::text_includes(&http, &crate::http_file::dir(&self.http))
+                .iter()
+                .map(|i| crate::http_file::replace_variables(i, &file_variables))
+                .collect();
+        let outputs = self.outputs.iter().flat_map(|o| o.values());
+        let auth = self.auth.iter().flat_map(AuthConfig::values);
+        let sign = self.sign.iter().flat_map(SigningConfig::values);
+        let multipart = self.multipart.iter().flat_map(MultipartConfig::values);
+        let cookies = self.cookies.iter().flat_map(CookiesConfig::values);
+        let poll = self.poll.iter().map(|p| p.until.as_str());
+        // queries are variable names rather than `{{ }}` templates
+        let expect = self
+            .expect
+            .iter()
+            .flatten()
+            .flat_map(Expectation::variables);
+
+        [http.as_str(), self.assertions.as_str()]
+            .into_iter()
+            .chain(includes.iter().map(String::as_str))
+            .chain(outputs.map(String::as_str))
+            .chain(auth)
+            .chain(sign)
+            .chain(multipart)
+            .chain(cookies)
+            .chain(poll)
+            .flat_map(crate::store::variables)
+            .chain(expect)
+            .map(String::from)
+            .collect()
+    }
+
+    fn outputs(&self) -> Vec<&str> {
+        self.outputs
+            .iter()
+            .flat_map(|o| o.keys())
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn retry(&self) -> Option<&RetryConfig> {
+        self.retry.as_ref()
+    }
+
+    fn client(&self) -> Option<&ClientConfig> {
+        self.client.as_ref()
+    }
+
     fn build<T: Store + RequestExecutor>(
-        self,
+        &self,
         hat: &T,
-        buffer: &mut String,
+        options: &RunOptions,
     ) -> anyhow::Result<HatTestOutput> {
-        match build(self, hat, buffer) {
+        match build(self, hat, options) {
             Ok(t) => Ok(t),
             Err(e) => Err(HatError::TestFailedToBuild(e.to_string()).into()),
         }
@@ -61,47 +184,268 @@ impl HatTestBuilder for TestConfig {
 }
 
 fn build<T: Store + RequestExecutor>(
-    hat_test_config: TestConfig,
+    hat_test_config: &TestConfig,
     hat: &T,
-    _: &mut String,
+    options: &RunOptions,
 ) -> Result<HatTestOutput, HatError> {
     // extract the raw http request from config
     // can either be a path to an .http file or the raw http request
-    let http_contents = crate::http_file::get_contents(hat_test_config.http)?;
+    let http_contents = crate::http_file::get_contents(hat_test_config.http.to_string())?;
     // replace variables in raw http request
     let http_contents = hat.match_and_replace(http_contents.as_str(), |v| v.as_value());
+    let dir = crate::http_file::dir(&hat_test_config.http);
+    let hydrate = |text: &str| hat.match_and_replace(text, |v| v.as_value());
+    // a `<@ path` include gets the file and system variables of the request as well
+    let file_variables = crate::http_file::file_variables(&hat_test_config.http)?;
+    let hydrate_include =
+        |text: &str| hydrate(&crate::http_file::replace_variables(text, &file_variables));
     log::debug!("HTTP: {}", &http_contents);
 
-    // parses the raw http request into something the http client can use
-    let request = crate::http_file::parse(http_contents.as_str())?;
-    let method = request.get_method().to_string();
-    let response = hat.execute(request)?;
-    let response_info = format!(
-        "{} {} {} {} {}",
-        response.status(),
-        response.status_text(),
-        method,
-        response.get_url(),
-        response.http_version()
-    );
+    let started = Instant::now();
+    let mut requests = 0;
+    let (request_line, response_info, response_store, poll) = loop {
+        // parses the raw http request into something the http client can use
+        let mut request = crate::http_file::parse(http_contents.as_str(), &dir, &hydrate_include)?;
+        if let Some(multipart) = &hat_test_config.multipart {
+            request.add_body(Body::Multipart(multipart.multipart(&hydrate)?));
+        }
+        if let Some(auth) = &hat_test_config.auth {
+            request = request.auth(auth.hydrate(hat));
+        }
+        if let Some(sign) = &hat_test_config.sign {
+            request = request.sign(sign.hydrate(hat));
+        }
+        let method = request.get_method().to_string();
+        let request_line = format!("{} {}", method, request.get_url());
+
+        if let (0, Some(cookies)) = (requests, &hat_test_config.cookies) {
+            let url = url::Url::parse(request.get_url()).map_err(|_| HatError::RequestBuilder)?;
+            // cookies set in a disabled jar would never be sent
+            let jar = hat.cookies().ok_or_else(|| {
+                HatError::Cookies(
+                    "[tests.cookies] needs the cookie jar, set `cookies = true` in [client] or [tests.client]"
+                        .to_string(),
+                )
+            })?;
+            cookies.hydrate(hat).apply(jar, &url);
+        }
+
+        let response = match (hat.execute(request), &hat_test_config.poll) {
+            (Ok(response), _) => response,
+            // a job that is not ready yet often answers with an error status, e.g. 404 or 503,
+            // or is not listening yet, errors building the request would never go away
+            (Err(e @ HatError::HttpResponse(_)), Some(poll)) if poll.can_continue(started) => {
+                requests += 1;
+                log::info!("polling again in {:?}: {}", poll.interval, e);
+                std::thread::sleep(poll.interval);
+                continue;
+            }
+            (Err(e), _) => return Err(e),
+        };
+        let mut response_info = ResponseSummary::new(&method, &response.response);
+        requests += 1;
+
+        log::debug!("{:#?}", &response.response);
+
+        // these stores contain the data from the response headers and body
+        // these should not persist across other tests unless specified in the `output` config
+        // any persistent store data gets handled at the end in `factory::outputs(...)`
+        let (response_store, timing) = factory::response(response)?;
+        response_info.timing = timing;
+        log::info!("{}", &response_info);
+
+        let Some(poll) = &hat_test_config.poll else {
+            break (request_line, response_info, response_store, None);
+        };
+
+        // the assertions only run against the response that satisfied the poll
+        let outcome = poll.check(&hat.compose(&response_store));
+        if outcome.passed {
+            break (request_line, response_info, response_store, Some(outcome));
+        }
 
-    log::info!("{}", &response_info);
-    log::debug!("{:#?}", &response);
+        if !poll.can_continue(started) {
+            let outcome = poll.timed_out(outcome, requests);
+            break (request_line, response_info, response_store, Some(outcome));
+        }
 
-    // these stores contain the data from the response headers and body
-    // these should not persist across other tests unless specified in the `output` config
-    // any persistent store data gets handled at the end in `factory::outputs(...)`
-    let response_store = factory::response(response)?;
+        log::info!("polling again in {:?}: {}", poll.interval, outcome.hydrated);
+        std::thread::sleep(poll.interval);
+    };
     let store_composed = hat.compose(&response_store);
 
-    let assertions =
-        store_composed.match_and_replace(&hat_test_config.assertions, |v| v.as_literal());
-    let assert = assertion::new(response_info, hat_test_config.description, assertions);
+    let assertions = hat_test_config
+        .assertions
+        .lines()
+        .map(|a| {
+            let (hydrated, variables) = store_composed.match_and_capture(a, |v| v.as_literal());
+            let variables = variables.into_iter().map(Substitution::from).collect();
+            (a.to_string(), hydrated, variables)
+        })
+        .collect();
+    let mut assert = assertion::new(
+        request_line,
+        response_info,
+        hat_test_config.description.clone(),
+        assertions,
+    );
+    assert.add_evaluated(poll);
+
+    if let Some(expect) = &hat_test_config.expect {
+        assert.add_evaluated(expect.iter().map(|e| e.assert(&store_composed)));
+    }
+
+    if let Some(schema) = &hat_test_config.schema {
+        assert.add_evaluated(crate::schema::validate(schema, &response_store)?);
+    }
+
+    if let Some(snapshot) = &hat_test_config.snapshot {
+        assert.add_evaluated(snapshot.assert(&response_store, options.update_snapshots)?);
+    }
 
-    let outputs = match hat_test_config.outputs {
+    let outputs = match &hat_test_config.outputs {
         Some(o) => Some(factory::outputs(&store_composed, o)?),
         None => None,
     };
 
     Ok((assert, outputs))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{client::Client, runner::HatRunner, store::StoreUnion, test::server};
+    use std::time::Duration;
+
+    #[test]
+    fn variables_come_from_every_hydrated_field() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("hat-variables-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        // the include reads a file variable of the .http file
+        std::fs::write(
+            dir.join("body.json"),
+            r#"{ "owner": "{{ owner }}", "id": "{{$uuid}}" }"#,
+        )?;
+        std::fs::write(
+            dir.join("create.http"),
+            "@owner = {{ ownerId }}\n\nPOST {{ base }}/posts\n\n<@ ./body.json",
+        )?;
+
+        let test: TestConfig = toml::from_str(&format!(
+            r#"
+http = "{}"
+assertions = "{{{{ status }}}} == 201"
+cookies = {{ set = {{ session = "{{{{ sessionId }}}}" }} }}
+poll = {{ until = "{{{{ body | state }}}} == {{{{ doneState }}}}" }}
+
+[[expect]]
+query = "postId"
+op = "exists"
+
+[[expect]]
+query = "body | title"
+op = "=="
+value = "{{{{ title }}}}"
+"#,
+            dir.join("create.http").to_string_lossy()
+        ))?;
+
+        let mut variables = test.variables();
+        variables.sort();
+        assert_eq!(
+            variables,
+            vec![
+                "base",
+                "body",
+                "body",
+                "doneState",
+                "ownerId",
+                "ownerId",
+                "postId",
+                "sessionId",
+                "status",
+                "title"
+            ]
+        );
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn poll_continues_after_a_failed_response() -> anyhow::Result<()> {
+        // a job that is not ready for the first request
+        let (port, server) = server::serve(vec![
+            server::response("503 Service Unavailable", &[], ""),
+            server::response(
+                "200 OK",
+                &["Content-Type: application/json"],
+                r#"{ "state": "done" }"#,
+            ),
+        ]);
+
+        let test: TestConfig = toml::from_str(&format!(
+            r#"
+http = "GET http://127.0.0.1:{}/jobs/1"
+assertions = "{{{{ status }}}} == 200"
+poll = {{ until = "{{{{ body | state }}}} == \"done\"", interval = "10ms", timeout = "5s" }}
+"#,
+            port
+        ))?;
+        let runner = HatRunner::new(
+            StoreUnion::MapStringToContent(Default::default()),
+            Client::new(Default::default()),
+            RunOptions::default(),
+        );
+
+        let (assertions, _) = build(&test, &runner, &RunOptions::default())?;
+        let outcome = assertions.assert();
+        assert!(outcome.passed(), "{:#?}", outcome.assertions);
+
+        server.join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn poll_stops_at_a_request_that_cannot_be_built() -> anyhow::Result<()> {
+        let test: TestConfig = toml::from_str(
+            r#"
+http = "GET http://127.0.0.1:1/jobs/1"
+poll = { until = "{{ body | state }} == \"done\"", interval = "10ms", timeout = "5s" }
+"#,
+        )?;
+        let runner = HatRunner::new(
+            StoreUnion::MapStringToContent(Default::default()),
+            // a certificate authority that cannot be read fails every request the same way
+            Client::new(toml::from_str(r#"tls = { ca = ["missing.pem"] }"#)?),
+            RunOptions::default(),
+        );
+
+        let started = Instant::now();
+        let result = build(&test, &runner, &RunOptions::default());
+        assert!(result.is_err());
+        assert!(!matches!(result, Err(HatError::HttpResponse(_))));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        Ok(())
+    }
+
+    #[test]
+    fn cookies_need_the_cookie_jar() -> anyhow::Result<()> {
+        let test: TestConfig = toml::from_str(
+            r#"
+http = "GET http://127.0.0.1:1/me"
+cookies = { set = { session = "expired" } }
+"#,
+        )?;
+        let runner = HatRunner::new(
+            StoreUnion::MapStringToContent(Default::default()),
+            Client::new(Default::default()),
+            RunOptions::default(),
+        );
+
+        // rejected before the request is sent
+        let result = build(&test, &runner, &RunOptions::default());
+        assert!(matches!(result, Err(HatError::Cookies(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+use crate::store::Store;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// changes a test makes to the cookie jar before its request is sent
+///
+/// ```toml
+/// [tests.cookies]
+/// clear = true
+/// set = { session = "expired" }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CookiesConfig {
+    /// removes every cookie from the jar
+    #[serde(default)]
+    pub clear: bool,
+    /// cookies added to the jar for the host of the request
+    #[serde(default)]
+    pub set: BTreeMap<String, String>,
+}
+
+impl CookiesConfig {
+    /// replaces the `{{ variables }}` in the cookie values
+    pub fn hydrate<S: Store>(&self, store: &S) -> CookiesConfig {
+        CookiesConfig {
+            clear: self.clear,
+            set: self
+                .set
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        store.match_and_replace(value, |v| v.as_value()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// every cookie value, used to find the variables a test reads
+    pub fn values(&self) -> Vec<&str> {
+        self.set.values().map(String::as_str).collect()
+    }
+
+    pub fn apply(&self, jar: &CookieJar, url: &url::Url) {
+        if self.clear {
+            jar.clear();
+        }
+
+        for (name, value) in &self.set {
+            jar.insert(Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: url.host_str().unwrap_or_default().to_lowercase(),
+                host_only: true,
+                path: "/".to_string(),
+                secure: false,
+                expires: None,
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    domain: String,
+    /// false when the cookie had a `Domain` attribute, which includes subdomains
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+impl Cookie {
+    /// parses a `Set-Cookie` header received from `url`
+    pub fn parse(header: &str, url: &url::Url) -> Option<Cookie> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: url.host_str()?.to_lowercase(),
+            host_only: true,
+            path: self::default_path(url.path()),
+            secure: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+
+            match key.trim().to_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_lowercase();
+                    // a server cannot set cookies for another site
+                    if !self::domain_matches(&cookie.domain, &domain) {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "max-age" => max_age = value.parse::<i64>().ok(),
+                "expires" => {
+                    cookie.expires = cookie.expires.or(httpdate::parse_http_date(value).ok())
+                }
+                _ => {}
+            }
+        }
+
+        // max-age takes precedence over expires
+        if let Some(seconds) = max_age {
+            cookie.expires = Some(if seconds <= 0 {
+                SystemTime::UNIX_EPOCH
+            } else {
+                SystemTime::now() + Duration::from_secs(seconds as u64)
+            });
+        }
+
+        Some(cookie)
+    }
+
+    fn expired(&self) -> bool {
+        self.expires.is_some_and(|e| e <= SystemTime::now())
+    }
+
+    fn matches(&self, url: &url::Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+
+        let domain = if self.host_only {
+            host == self.domain
+        } else {
+            self::domain_matches(&host, &self.domain)
+        };
+
+        let path = url.path();
+        let path = path == self.path
+            || (path.starts_with(&self.path)
+                && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/')));
+
+        domain && path && (!self.secure || url.scheme() == "https") && !self.expired()
+    }
+}
+
+/// cookies shared by every test in a run
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+impl CookieJar {
+    /// stores every `Set-Cookie` header of a response from `url`
+    pub fn store<'a, I: IntoIterator<Item = &'a str>>(&self, url: &url::Url, headers: I) {
+        for cookie in headers.into_iter().filter_map(|h| Cookie::parse(h, url)) {
+            self.insert(cookie);
+        }
+    }
+
+    /// replaces the cookie with the same name, domain, and path in place, expired cookies are removed
+    pub fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+        let existing = cookies.iter().position(|c| {
+            c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path
+        });
+
+        match (existing, cookie.expired()) {
+            (Some(i), true) => {
+                cookies.remove(i);
+            }
+            (Some(i), false) => cookies[i] = cookie,
+            (None, true) => {}
+            (None, false) => cookies.push(cookie),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.cookies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// `Cookie` header value for a request to `url`, longer paths first
+    pub fn header(&self, url: &url::Url) -> Option<String> {
+        let cookies = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+        let mut matching: Vec<&Cookie> = cookies.iter().filter(|c| c.matches(url)).collect();
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+
+        if matching.is_empty() {
+            return None;
+        }
+
+        Some(
+            matching
+                .iter()
+                .map(|c| format!("{}={}", c.name, c.value))
+                .collect::<Vec<String>>()
+                .join("; "),
+        )
+    }
+}
+
+/// `host` is `domain` or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// directory of the request path, e.g. `/api/users/1` -> `/api/users`
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn url(url: &str) -> url::Url {
+        url::Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn cookies_are_sent_to_matching_requests() {
+        let jar = CookieJar::default();
+        jar.store(
+            &url("https://api.example.com/auth/login"),
+            [
+                "session=abc; Path=/; HttpOnly",
+                "scoped=1",
+                "shared=2; Domain=example.com; Path=/; Secure",
+                "tracking=3; Domain=other.com",
+            ],
+        );
+
+        assert_eq!(
+            jar.header(&url("https://api.example.com/users")).as_deref(),
+            Some("session=abc; shared=2")
+        );
+        assert_eq!(
+            jar.header(&url("https://api.example.com/auth/logout"))
+                .as_deref(),
+            Some("scoped=1; session=abc; shared=2")
+        );
+        assert_eq!(
+            jar.header(&url("https://www.example.com/")).as_deref(),
+            Some("shared=2")
+        );
+        assert_eq!(jar.header(&url("http://www.example.com/")), None);
+        assert_eq!(jar.header(&url("https://other.com/")), None);
+    }
+
+    #[test]
+    fn cookies_are_replaced_and_expired() {
+        let jar = CookieJar::default();
+        let login = url("http://localhost/login");
+
+        jar.store(&login, ["session=abc", "theme=dark"]);
+        jar.store(&login, ["session=def"]);
+        assert_eq!(
+            jar.header(&login).as_deref(),
+            Some("session=def; theme=dark")
+        );
+
+        jar.store(
+            &login,
+            [
+                "session=; Max-Age=0",
+                "theme=dark; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            ],
+        );
+        assert_eq!(jar.header(&login), None);
+    }
+
+    #[test]
+    fn tests_can_clear_and_set_cookies() {
+        let jar = CookieJar::default();
+        let users = url("http://localhost/users");
+        jar.store(&users, ["session=abc"]);
+
+        let config: CookiesConfig =
+            toml::from_str("clear = true\nset = { session = \"expired\" }").unwrap();
+        config.apply(&jar, &users);
+
+        assert_eq!(jar.header(&users).as_deref(), Some("session=expired"));
+    }
+}
@@ -14,4 +14,18 @@ pub enum HatError {
     RequestBuilder,
     #[error("response failed: {0}")]
     HttpResponse(String),
+    #[error("json schema failed: {0}")]
+    Schema(String),
+    #[error("snapshot failed: {0}")]
+    Snapshot(String),
+    #[error("tls failed: {0}")]
+    Tls(String),
+    #[error("proxy failed: {0}")]
+    Proxy(String),
+    #[error("auth failed: {0}")]
+    Auth(String),
+    #[error("signing failed: {0}")]
+    Signing(String),
+    #[error("cookies failed: {0}")]
+    Cookies(String),
 }
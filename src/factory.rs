@@ -1,22 +1,24 @@
 use crate::{
     error::HatError,
     query::Content,
+    report::Timing,
+    runner::TimedResponse,
     store::{Store, StoreUnion},
 };
 use std::collections::HashMap;
 
 pub fn outputs<S: Store>(
     store: &S,
-    outputs_from_config: HashMap<String, String>,
+    outputs_from_config: &HashMap<String, String>,
 ) -> Result<StoreUnion, HatError> {
     // create map for outputs
     // ensure variables used in outputs are hydrated from the latest store
     // ?? evaluate the hydrated output content?
     // finally, assign it to the output key
     let mut evaluated_outputs = HashMap::<String, Content>::default();
-    for (key, value) in outputs_from_config.into_iter() {
-        let value = store.match_and_replace(&value, |v| v.as_value());
-        evaluated_outputs.insert(key, Content::new(value));
+    for (key, value) in outputs_from_config {
+        let value = store.match_and_replace(value, |v| v.as_value());
+        evaluated_outputs.insert(key.to_string(), Content::new(value));
     }
 
     log::info!("OUTPUTS: {:#?}", evaluated_outputs);
@@ -24,8 +26,14 @@ pub fn outputs<S: Store>(
     Ok(StoreUnion::MapStringToContent(evaluated_outputs))
 }
 
-pub fn response(response: ureq::Response) -> Result<StoreUnion, HatError> {
+/// reads the response into a store, along with how long the response took
+pub fn response(timed: TimedResponse) -> Result<(StoreUnion, Timing), HatError> {
     let mut store = HashMap::<String, Content>::default();
+    let TimedResponse {
+        response,
+        started,
+        ttfb,
+    } = timed;
 
     let response_header = internal::store_from_response(&mut store, &response);
     if response_header.is_err() {
@@ -36,7 +44,13 @@ pub fn response(response: ureq::Response) -> Result<StoreUnion, HatError> {
         log::error!("{:?}", response_header);
     }
 
-    Ok(StoreUnion::MapStringToContent(store))
+    let timing = Timing {
+        duration: started.elapsed(),
+        ttfb,
+    };
+    internal::store_from_timing(&mut store, &timing);
+
+    Ok((StoreUnion::MapStringToContent(store), timing))
 }
 
 pub fn store_from_response_body(
@@ -65,9 +79,21 @@ pub fn store_from_response_body(
 }
 
 mod internal {
-    use crate::query::Content;
+    use crate::{cookies::Cookie, query::Content, report::Timing};
     use std::collections::HashMap;
 
+    /// durations are stored in milliseconds, e.g. `{{ duration }} < 500`
+    pub fn store_from_timing(buffer: &mut HashMap<String, Content>, timing: &Timing) {
+        buffer.insert(
+            "duration".to_string(),
+            Content::Json(timing.duration.as_millis().to_string()),
+        );
+        buffer.insert(
+            "ttfb".to_string(),
+            Content::Json(timing.ttfb.as_millis().to_string()),
+        );
+    }
+
     pub fn store_from_response(
         buffer: &mut HashMap<String, Content>,
         response: &ureq::Response,
@@ -100,6 +126,21 @@ mod internal {
         let json = json.dump();
         buffer.insert("headers".to_string(), Content::Json(json));
 
+        if let Ok(url) = url::Url::parse(response.get_url()) {
+            let mut cookies = json::JsonValue::new_object();
+            for cookie in response
+                .all("set-cookie")
+                .into_iter()
+                .filter_map(|h| Cookie::parse(h, &url))
+            {
+                cookies[cookie.name] = cookie.value.into();
+            }
+
+            if !cookies.is_empty() {
+                buffer.insert("cookies".to_string(), Content::Json(cookies.dump()));
+            }
+        }
+
         log::debug!("HEADERS: {:#?}", &buffer);
 
         Ok(())
@@ -67,6 +67,7 @@ impl Endpoint {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod test {
     use super::*;
 
@@ -0,0 +1,224 @@
+use super::{parser, variables};
+
+/// a request in a .http file with several requests
+#[derive(Debug, PartialEq, Eq)]
+pub struct Section<'a> {
+    /// from a `# @name <name>` or `// @name <name>` line above the request line
+    pub name: Option<&'a str>,
+    pub contents: String,
+}
+
+/// splits a .http file into the requests separated by `###` lines
+///
+/// ```http
+/// ### anything after the separator is ignored
+/// # @name createUser
+/// POST {{base}}/users
+///
+/// ###
+/// # @name getUser
+/// GET {{base}}/users/{{userId}}
+/// ```
+///
+/// a file without `###` is a single request and its contents are returned unchanged
+pub fn sections(contents: &str) -> Vec<Section<'_>> {
+    if !contents.lines().any(self::is_separator) {
+        return vec![Section {
+            name: self::request_name(contents.lines()),
+            contents: contents.to_string(),
+        }];
+    }
+
+    let mut sections = Vec::new();
+    let mut lines = contents.lines().peekable();
+
+    while lines.peek().is_some() {
+        let mut name = None;
+        let mut request: Vec<&str> = Vec::new();
+
+        for line in lines.by_ref() {
+            if self::is_separator(line) {
+                break;
+            }
+
+            // blank lines after the separator are dropped
+            if request.is_empty() && line.trim().is_empty() {
+                continue;
+            }
+            // @name can follow other comments and variables, as long as it is above the request line
+            if !request.iter().any(|l| self::is_request(l)) {
+                if let Some(n) = self::name(line) {
+                    name = Some(n);
+                    continue;
+                }
+            }
+
+            request.push(line);
+        }
+
+        while request.last().is_some_and(|l| l.trim().is_empty()) {
+            request.pop();
+        }
+
+        // sections with only variables or comments are not requests
+        if request.iter().any(|l| self::is_request(l)) {
+            sections.push(Section {
+                name,
+                contents: request.join("\n"),
+            });
+        }
+    }
+
+    sections
+}
+
+/// the `@name = value` lines outside of the requests, e.g. at the top of the file
+pub fn variables(contents: &str) -> Vec<(String, String)> {
+    let mut in_request = false;
+    let mut lines = Vec::new();
+
+    for line in contents.lines() {
+        if self::is_separator(line) {
+            in_request = false;
+        } else if !in_request && variables::is_file_variable(line) {
+            lines.push(line);
+        } else if self::is_request(line) {
+            in_request = true;
+        }
+    }
+
+    variables::file_variables(&lines)
+}
+
+/// a line that is not blank, a comment, or a variable
+fn is_request(line: &str) -> bool {
+    !line.trim().is_empty() && !parser::is_comment(line) && !variables::is_file_variable(line)
+}
+
+fn is_separator(line: &str) -> bool {
+    line.starts_with("###")
+}
+
+/// the `@name` among the comments and variables above the request line
+fn request_name<'a, I: Iterator<Item = &'a str>>(lines: I) -> Option<&'a str> {
+    lines
+        .take_while(|l| !self::is_request(l))
+        .find_map(self::name)
+}
+
+/// `# @name createUser` or `// @name createUser`
+fn name(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let comment = line.strip_prefix('#').or_else(|| line.strip_prefix("//"))?;
+    let name = comment.trim().strip_prefix("@name")?;
+
+    // `@names` is not a name
+    if !name.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some(name.trim()).filter(|n| !n.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn file_without_separator_is_one_request() {
+        let http = "GET https://example.com\n\n{}\n";
+        assert_eq!(
+            sections(http),
+            vec![Section {
+                name: None,
+                contents: http.to_string()
+            }]
+        );
+    }
+
+    #[test]
+    pub fn requests_are_split_and_named() {
+        let http = r#"### create a user
+# @name createUser
+POST https://example.com/users
+Content-Type: application/json
+
+{ "name": "hat" }
+
+###
+
+// @name getUser
+GET https://example.com/users/1
+
+###
+GET https://example.com/health
+###
+"#;
+        let sections = sections(http);
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].name, Some("createUser"));
+        assert_eq!(
+            sections[0].contents,
+            "POST https://example.com/users\nContent-Type: application/json\n\n{ \"name\": \"hat\" }"
+        );
+        assert_eq!(sections[1].name, Some("getUser"));
+        assert_eq!(sections[1].contents, "GET https://example.com/users/1");
+        assert_eq!(sections[2].name, None);
+    }
+
+    #[test]
+    pub fn name_can_follow_comments_and_variables() {
+        let http = r#"###
+# creates a user for the tests below
+@contentType = application/json
+# @name createUser
+POST https://example.com/users
+Content-Type: {{contentType}}
+# @name is not the name once the request started
+
+###
+GET https://example.com/health
+"#;
+        let sections = sections(http);
+
+        assert_eq!(sections[0].name, Some("createUser"));
+        assert_eq!(
+            sections[0].contents,
+            "# creates a user for the tests below\n@contentType = application/json\nPOST https://example.com/users\nContent-Type: {{contentType}}\n# @name is not the name once the request started"
+        );
+        assert_eq!(sections[1].name, None);
+    }
+
+    #[test]
+    pub fn file_without_separator_can_be_named() {
+        let http = "# log in\n# @name login\nPOST https://example.com/login\n";
+        assert_eq!(
+            sections(http),
+            vec![Section {
+                name: Some("login"),
+                contents: http.to_string()
+            }]
+        );
+    }
+
+    #[test]
+    pub fn variables_are_defined_outside_of_requests() {
+        let http = r#"@base = https://example.com
+
+###
+@version = v2
+GET {{base}}/{{version}}/users
+
+@body = not a variable
+"#;
+        assert_eq!(sections(http).len(), 1);
+        assert_eq!(
+            variables(http),
+            vec![
+                ("base".to_string(), "https://example.com".to_string()),
+                ("version".to_string(), "v2".to_string()),
+            ]
+        );
+    }
+}
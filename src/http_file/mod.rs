@@ -1,10 +1,14 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 mod endpoint;
+mod file;
+mod multipart;
 mod parser;
 mod request_builder;
+mod variables;
 
-pub use request_builder::RequestBuilder;
+pub use multipart::{Multipart, MultipartConfig};
+pub use request_builder::{Body, RequestBuilder};
 
 #[derive(thiserror::Error, Debug)]
 pub enum HttpLexerError {
@@ -21,27 +25,123 @@ pub enum HttpLexerError {
     EndpointError(#[from] endpoint::EndpointError),
     #[error("{0}")]
     IO(#[from] std::io::Error),
+    #[error("{path} has no request named '{name}'")]
+    MissingRequest { path: String, name: String },
+    #[error("{path} has {count} requests, choose one with {path}#<name>")]
+    AmbiguousRequest { path: String, count: usize },
+    /// a missing file is reported when the request is parsed rather than when it is sent
+    #[error("could not include {path} in the body: {source}")]
+    Include {
+        path: String,
+        source: std::io::Error,
+    },
 }
 
+/// `input` is a raw http request, a path to a .http file, or `path/to/file.http#<name>` for
+/// the request named with `# @name <name>` in a file with several requests
+///
+/// `@name = value` file variables and `{{$guid}}`-style system variables are replaced
 pub fn get_contents(input: String) -> Result<String, HttpLexerError> {
-    Ok(if input.ends_with(".http") {
-        std::fs::read_to_string(input.as_str())?
-    } else {
-        input
-    })
+    let (request, variables) = self::select(&input)?;
+    Ok(variables::replace(&request, &variables))
+}
+
+/// the `@name = value` variables of the .http file `input` refers to, e.g. to replace in the
+/// contents of a `<@ path` include the same way as in the request
+pub fn file_variables(input: &str) -> Result<Vec<(String, String)>, HttpLexerError> {
+    Ok(self::select(input)?.1)
+}
+
+/// replaces the file variables and the `{{$guid}}`-style system variables
+pub fn replace_variables(text: &str, file_variables: &[(String, String)]) -> String {
+    variables::replace(text, file_variables)
+}
+
+/// the request `input` refers to and the variables of its file
+fn select(input: &str) -> Result<(String, Vec<(String, String)>), HttpLexerError> {
+    let (path, name) = match input.rsplit_once('#') {
+        Some((path, name)) if path.ends_with(".http") && !path.contains(char::is_whitespace) => {
+            (path, Some(name))
+        }
+        _ if input.ends_with(".http") => (input, None),
+        _ => return Ok((input.to_string(), file::variables(input))),
+    };
+
+    let contents = std::fs::read_to_string(path)?;
+    let mut sections = file::sections(&contents);
+
+    let request = match (name, sections.len()) {
+        (Some(name), _) => sections
+            .into_iter()
+            .find(|s| s.name == Some(name))
+            .ok_or_else(|| HttpLexerError::MissingRequest {
+                path: path.to_string(),
+                name: name.to_string(),
+            })?,
+        (None, 0) => {
+            return Err(HttpLexerError::MalformedHttpFile(
+                "every request between the ### separators is empty",
+            ))
+        }
+        (None, 1) => sections.remove(0),
+        (None, count) => {
+            return Err(HttpLexerError::AmbiguousRequest {
+                path: path.to_string(),
+                count,
+            })
+        }
+    };
+
+    Ok((request.contents, file::variables(&contents)))
 }
 
-pub fn parse(input: &str) -> Result<RequestBuilder, HttpLexerError> {
+/// directory that `< path` body includes are relative to, the directory of the .http file
+/// or the current directory for a raw http request
+pub fn dir(input: &str) -> PathBuf {
+    let path = match input.rsplit_once('#') {
+        Some((path, _)) if path.ends_with(".http") => path,
+        _ => input,
+    };
+
+    match Path::new(path).parent() {
+        Some(parent) if path.ends_with(".http") => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// the contents of every `<@ path` text include in a request, a file that cannot be read is
+/// reported when the test is built
+pub fn text_includes(contents: &str, dir: &Path) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("<@"))
+        .filter(|path| path.starts_with(char::is_whitespace))
+        .filter_map(|path| std::fs::read_to_string(dir.join(path.trim())).ok())
+        .collect()
+}
+
+/// `dir` is the directory `< path` body includes are relative to, `hydrate` replaces the
+/// variables of a `<@ path` body include
+pub fn parse(
+    input: &str,
+    dir: &Path,
+    hydrate: &dyn Fn(&str) -> String,
+) -> Result<RequestBuilder, HttpLexerError> {
     if input.ends_with(".http") {
         parse_from_path(input)
     } else {
-        parse_from_utf8(input)
+        parser::request(input, dir, hydrate)
     }
 }
 
 pub fn parse_from_path<P: AsRef<Path>>(path: P) -> Result<RequestBuilder, HttpLexerError> {
-    let buffer = std::fs::read(path)?;
-    let builder = parse_from_utf8(buffer)?;
+    let buffer = std::fs::read(path.as_ref())?;
+    let contents = String::from_utf8_lossy(&buffer);
+    let dir = path.as_ref().parent().unwrap_or(Path::new("."));
+    let file_variables = file::variables(&contents);
+    let builder = parser::request(&contents, dir, &|text| {
+        variables::replace(text, &file_variables)
+    })?;
     Ok(builder)
 }
 
@@ -60,6 +160,18 @@ pub fn parse_from_path<P: AsRef<Path>>(path: P) -> Result<RequestBuilder, HttpLe
 ///newline  (required)  |
 ///body     (optional)  | <BODY>
 ///```
+///
+/// blank lines before the request line are skipped, and lines starting with `#` or `//`
+/// are comments anywhere before the body
+///
+/// the VS Code REST Client and JetBrains HTTP Client dialect is also accepted
+///```http
+///version  (optional)  | <METHOD> <URL> HTTP/1.1
+///query    (optional)  |     ?<NAME>=<VALUE>
+///                     |     &<NAME>=<VALUE>
+///include  (optional)  | < <PATH>
+///```
+#[allow(dead_code)]
 pub fn parse_from_utf8<T: AsRef<[u8]>>(
     http_file_buffer: T,
 ) -> Result<RequestBuilder, HttpLexerError> {
@@ -70,7 +182,7 @@ pub fn parse_from_utf8<T: AsRef<[u8]>>(
     // if index == 0, then HttpToken::Request(..)
     // if index > 0, then HttpToken::Header(..)
     // until line == "\n", then HttpToken::Body(..)
-    let builder = parser::request(&contents)?;
+    let builder = parser::request(&contents, Path::new("."), &|text| text.to_string())?;
 
     Ok(builder)
 }
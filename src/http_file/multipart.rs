@@ -0,0 +1,385 @@
+use super::{variables, Body, HttpLexerError};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// a `multipart/form-data` body, RFC 7578
+///
+/// the boundary is generated, the `Content-Type` header is set from it when the body is added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    body: Body,
+}
+
+impl Multipart {
+    pub fn new(parts: Vec<Part>) -> Self {
+        Self {
+            boundary: format!("hat-boundary-{}", variables::uuid().replace('-', "")),
+            parts,
+        }
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// every part between the boundaries, files are read into memory
+    pub fn encode(&self) -> std::io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        for part in &self.parts {
+            bytes.extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+            bytes.extend_from_slice(
+                format!(
+                    r#"Content-Disposition: form-data; name="{}""#,
+                    self::escape(&part.name)
+                )
+                .as_bytes(),
+            );
+            if let Some(filename) = &part.filename {
+                bytes.extend_from_slice(
+                    format!(r#"; filename="{}""#, self::escape(filename)).as_bytes(),
+                );
+            }
+            bytes.extend_from_slice(b"\r\n");
+            if let Some(content_type) = &part.content_type {
+                bytes.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+            }
+            bytes.extend_from_slice(b"\r\n");
+            bytes.extend_from_slice(&part.body.bytes()?);
+            bytes.extend_from_slice(b"\r\n");
+        }
+
+        bytes.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        Ok(bytes)
+    }
+}
+
+impl Part {
+    pub fn text(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            filename: None,
+            content_type: None,
+            body: Body::Text(value.to_string()),
+        }
+    }
+
+    /// the filename and `Content-Type` come from the path
+    pub fn file(name: &str, path: PathBuf) -> Self {
+        Self {
+            name: name.to_string(),
+            filename: path.file_name().map(|f| f.to_string_lossy().to_string()),
+            content_type: Some(self::content_type(&path).to_string()),
+            body: Body::File(path),
+        }
+    }
+}
+
+/// fields and files sent as a `multipart/form-data` body, replacing the body of the .http request
+///
+/// ```toml
+/// [tests.multipart.fields]
+/// description = "avatar for {{ userId }}"
+///
+/// [tests.multipart.files]
+/// avatar = "example/local/bodies/avatar.png"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct MultipartConfig {
+    #[serde(default)]
+    fields: BTreeMap<String, String>,
+    /// paths are relative to the working directory
+    #[serde(default)]
+    files: BTreeMap<String, String>,
+}
+
+impl MultipartConfig {
+    /// fields first, then files, each in name order
+    pub fn multipart(&self, hydrate: &dyn Fn(&str) -> String) -> Result<Multipart, HttpLexerError> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| Ok::<_, HttpLexerError>(Part::text(name, &hydrate(value))));
+
+        let files = self.files.iter().map(|(name, path)| {
+            let path = PathBuf::from(hydrate(path));
+            std::fs::metadata(&path).map_err(|source| HttpLexerError::Include {
+                path: path.to_string_lossy().to_string(),
+                source,
+            })?;
+            Ok(Part::file(name, path))
+        });
+
+        Ok(Multipart::new(
+            fields.chain(files).collect::<Result<_, _>>()?,
+        ))
+    }
+
+    /// every field value and file path, used to find the variables a test reads
+    pub fn values(&self) -> Vec<&str> {
+        self.fields
+            .values()
+            .chain(self.files.values())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// the boundary of a `multipart/form-data; boundary=<boundary>` content type
+pub fn boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    if !params
+        .next()?
+        .trim()
+        .eq_ignore_ascii_case("multipart/form-data")
+    {
+        return None;
+    }
+
+    params
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, v)| v.trim().trim_matches('"').to_string())
+        .filter(|b| !b.is_empty())
+}
+
+/// the parts of a .http body written with the boundary of its `Content-Type` header
+///
+/// ```http
+/// --boundary
+/// Content-Disposition: form-data; name="description"
+///
+/// my avatar
+/// --boundary
+/// Content-Disposition: form-data; name="avatar"; filename="avatar.png"
+/// Content-Type: image/png
+///
+/// < ./avatar.png
+/// --boundary--
+/// ```
+///
+/// `include` turns a part's contents into its body, e.g. `< path` into a file
+pub fn parse(
+    body: &str,
+    boundary: &str,
+    include: &dyn Fn(String) -> Result<Body, HttpLexerError>,
+) -> Result<Multipart, HttpLexerError> {
+    let delimiter = format!("--{}", boundary);
+    let end = format!("--{}--", boundary);
+    let mut parts = Vec::new();
+    // everything before the first boundary is a preamble and ignored
+    let mut current: Option<Vec<&str>> = None;
+
+    for line in body.lines() {
+        let trimmed = line.trim_end();
+        if trimmed == delimiter || trimmed == end {
+            if let Some(lines) = current.take() {
+                parts.push(self::part(&lines, include)?);
+            }
+            if trimmed == end {
+                break;
+            }
+            current = Some(Vec::new());
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+
+    if let Some(lines) = current {
+        parts.push(self::part(&lines, include)?);
+    }
+
+    Ok(Multipart::new(parts))
+}
+
+fn part(
+    lines: &[&str],
+    include: &dyn Fn(String) -> Result<Body, HttpLexerError>,
+) -> Result<Part, HttpLexerError> {
+    let blank = lines.iter().position(|l| l.trim().is_empty());
+    let (headers, contents) = match blank {
+        Some(i) => (&lines[..i], &lines[i + 1..]),
+        None => (lines, &[][..]),
+    };
+
+    let header = |name: &str| {
+        headers.iter().find_map(|h| {
+            let (k, v) = h.split_once(':')?;
+            k.trim().eq_ignore_ascii_case(name).then(|| v.trim())
+        })
+    };
+    let disposition = header("content-disposition").unwrap_or_default();
+    let param = |name: &str| {
+        disposition
+            .split(';')
+            .skip(1)
+            .filter_map(|p| p.split_once('='))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().trim_matches('"').to_string())
+    };
+
+    Ok(Part {
+        name: param("name").ok_or(HttpLexerError::MalformedHttpFile(
+            "every multipart part needs a Content-Disposition header with a name",
+        ))?,
+        filename: param("filename"),
+        content_type: header("content-type").map(String::from),
+        body: include(contents.join("\n"))?,
+    })
+}
+
+/// `"` and line breaks would end the quoted name early
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// guessed from the extension, most servers only check it for images
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn parts_are_encoded_between_boundaries() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("hat-avatar-{}.png", std::process::id()));
+        std::fs::write(&path, [0x89, b'P', b'N', b'G'])?;
+
+        let multipart = Multipart::new(vec![
+            Part::text("description", "my avatar"),
+            Part::file("avatar", path.clone()),
+        ]);
+        let boundary = multipart.boundary.clone();
+        assert_eq!(
+            boundary,
+            super::boundary(&multipart.content_type()).unwrap()
+        );
+
+        let mut expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"description\"\r\n\r\nmy avatar\r\n--{b}\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"{}\"\r\nContent-Type: image/png\r\n\r\n",
+            path.file_name().unwrap().to_string_lossy(),
+            b = boundary
+        )
+        .into_bytes();
+        expected.extend_from_slice(&[0x89, b'P', b'N', b'G']);
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        assert_eq!(multipart.encode()?, expected);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    pub fn http_body_is_split_into_parts() -> anyhow::Result<()> {
+        let body = r#"ignored preamble
+--WebAppBoundary
+Content-Disposition: form-data; name="description"
+
+two
+lines
+--WebAppBoundary
+Content-Disposition: form-data; name="avatar"; filename="avatar.png"
+Content-Type: image/png
+
+< ./avatar.png
+--WebAppBoundary--"#;
+        let include = |contents: String| {
+            Ok(match contents.strip_prefix("< ") {
+                Some(path) => Body::File(PathBuf::from(path)),
+                None => Body::Text(contents),
+            })
+        };
+
+        let multipart = parse(body, "WebAppBoundary", &include)?;
+        assert_eq!(
+            multipart.parts,
+            vec![
+                Part::text("description", "two\nlines"),
+                Part {
+                    name: "avatar".to_string(),
+                    filename: Some("avatar.png".to_string()),
+                    content_type: Some("image/png".to_string()),
+                    body: Body::File(PathBuf::from("./avatar.png")),
+                }
+            ]
+        );
+        // a new boundary is generated for the request
+        assert_ne!(multipart.boundary, "WebAppBoundary");
+
+        let missing_name = "--b\nContent-Disposition: form-data\n\nvalue\n--b--";
+        assert!(parse(missing_name, "b", &include).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_fields_and_files_are_hydrated() -> anyhow::Result<()> {
+        let config: MultipartConfig = toml::from_str(
+            r#"
+[fields]
+description = "avatar for {{ userId }}"
+
+[files]
+avatar = "Cargo.toml"
+"#,
+        )?;
+        assert_eq!(
+            config.values(),
+            vec!["avatar for {{ userId }}", "Cargo.toml"]
+        );
+
+        let multipart = config.multipart(&|text| text.replace("{{ userId }}", "7"))?;
+        assert_eq!(
+            multipart.parts,
+            vec![
+                Part::text("description", "avatar for 7"),
+                Part {
+                    name: "avatar".to_string(),
+                    filename: Some("Cargo.toml".to_string()),
+                    content_type: Some("application/octet-stream".to_string()),
+                    body: Body::File(PathBuf::from("Cargo.toml")),
+                }
+            ]
+        );
+
+        let missing: MultipartConfig = toml::from_str("[files]\navatar = \"missing.png\"")?;
+        assert!(missing.multipart(&|text| text.to_string()).is_err());
+        Ok(())
+    }
+}
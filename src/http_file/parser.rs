@@ -1,8 +1,10 @@
-use super::{HttpLexerError, RequestBuilder};
+use super::{multipart, variables, Body, HttpLexerError, RequestBuilder};
+use std::path::Path;
 
-/// <METHOD> <URL>
-/// METHOD = letters only
-/// URL    = https://www.rfc-editor.org/rfc/rfc9110.html#section-4.1
+/// <METHOD> <URL> [<VERSION>]
+/// METHOD  = letters only
+/// URL     = https://www.rfc-editor.org/rfc/rfc9110.html#section-4.1
+/// VERSION = HTTP/1.1, ignored since the http client picks the version
 pub fn parse_request_line(line: &str) -> Option<[&str; 2]> {
     let mut line = line.split(' ');
     let method = line.next()?;
@@ -12,6 +14,12 @@ pub fn parse_request_line(line: &str) -> Option<[&str; 2]> {
         return None;
     }
 
+    match (line.next(), line.next()) {
+        (None, _) => {}
+        (Some(version), None) if version.starts_with("HTTP/") => {}
+        _ => return None,
+    }
+
     Some([method, url])
 }
 
@@ -31,31 +39,70 @@ pub fn parse_header_line(line: &str) -> Option<[&str; 2]> {
     Some([header_name, header_value])
 }
 
-pub fn request(contents: &str) -> Result<RequestBuilder, HttpLexerError> {
-    let mut lines = contents.lines();
-    let mut row = 1;
+/// a whole line starting with `#` or `//`
+pub fn is_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with('#') || line.starts_with("//")
+}
+
+/// `dir` is the directory `< path` body includes are relative to, `hydrate` replaces the
+/// variables of a `<@ path` body include
+pub fn request(
+    contents: &str,
+    dir: &Path,
+    hydrate: &dyn Fn(&str) -> String,
+) -> Result<RequestBuilder, HttpLexerError> {
+    let mut lines = contents.lines().peekable();
+    let mut row = 0;
     let col = 1;
 
-    let first_line = if let Some(x) = lines.next() {
-        log::debug!("[{} _request]: {}", row, x);
-        x
-    } else {
-        return Err(HttpLexerError::MalformedHttpFile(
-            "request line is missing\nmust have at least one line with a <METHOD> <URL>",
-        ));
+    // blank lines, comments, and file variables before the request line are skipped
+    let (first_line, first_col) = loop {
+        row += 1;
+
+        match lines.next() {
+            Some(x)
+                if x.trim().is_empty() || self::is_comment(x) || variables::is_file_variable(x) =>
+            {
+                log::debug!("[{} _comment]: {}", row, x);
+            }
+            Some(x) => {
+                log::debug!("[{} _request]: {}", row, x);
+                let trimmed = x.trim();
+                break (trimmed, x.len() - x.trim_start().len() + 1);
+            }
+            None => {
+                return Err(HttpLexerError::MalformedHttpFile(
+                    "request line is missing\nmust have at least one line with a <METHOD> <URL>",
+                ))
+            }
+        }
     };
 
     let [method, url] =
         self::parse_request_line(first_line).ok_or(HttpLexerError::MalformedLine {
             row,
-            col,
+            col: first_col,
             content: first_line.to_string(),
             reason: std::borrow::Cow::Borrowed(
                 "a method and a url path is expected\n<METHOD> <URL>",
             ),
         })?;
 
-    let mut builder = RequestBuilder::new(method, url)?;
+    // the query can continue on the next lines
+    //     ?page=2
+    //     &pageSize=10
+    let mut url = url.to_string();
+    while let Some(line) = lines.next_if(|l| {
+        let l = l.trim_start();
+        l.starts_with('?') || l.starts_with('&')
+    }) {
+        row += 1;
+        log::debug!("[{} ___query]: {}", row, line);
+        url.push_str(line.trim());
+    }
+
+    let mut builder = RequestBuilder::new(method, &url)?;
 
     // parse headers
     let mut current = lines.next();
@@ -67,6 +114,12 @@ pub fn request(contents: &str) -> Result<RequestBuilder, HttpLexerError> {
             break;
         }
 
+        if self::is_comment(line) {
+            log::debug!("[{} _comment]: {}", row, line);
+            current = lines.next();
+            continue;
+        }
+
         log::debug!("[{} __header]: {}", row, line);
 
         let [header_name, header_value] =
@@ -106,12 +159,52 @@ does not conform to header formatting rule(s)
         // remove extra '\n'
         remaining.pop();
         log::debug!("[{}+ ___body]: {}", row + 1, &remaining);
-        builder.add_body(remaining);
+        let include = |contents| self::include(contents, dir, hydrate);
+        // `< path` includes also work inside the parts of a multipart body
+        let body = match builder
+            .get_header("content-type")
+            .and_then(multipart::boundary)
+        {
+            Some(boundary) => Body::Multipart(multipart::parse(&remaining, &boundary, &include)?),
+            None => include(remaining)?,
+        };
+        builder.add_body(body);
     }
 
     Ok(builder)
 }
 
+/// a body that is only `< path/to/file` is sent from the file as is, `<@ path/to/file` is read
+/// as text and its `{{ }}` variables are replaced
+fn include(
+    body: String,
+    dir: &Path,
+    hydrate: &dyn Fn(&str) -> String,
+) -> Result<Body, HttpLexerError> {
+    let line = body.trim();
+    let (path, text) = match line.strip_prefix("<@").or_else(|| line.strip_prefix('<')) {
+        Some(path) if !line.contains('\n') && path.starts_with(char::is_whitespace) => {
+            (path, line.starts_with("<@"))
+        }
+        _ => return Ok(Body::Text(body)),
+    };
+
+    let path = dir.join(path.trim());
+    log::debug!("[body include]: {}", path.to_string_lossy());
+    let error = |source| HttpLexerError::Include {
+        path: path.to_string_lossy().to_string(),
+        source,
+    };
+
+    if text {
+        let contents = std::fs::read_to_string(&path).map_err(error)?;
+        return Ok(Body::Text(hydrate(&contents)));
+    }
+
+    std::fs::metadata(&path).map_err(error)?;
+    Ok(Body::File(path))
+}
+
 #[cfg(test)]
 mod test {
     use super::super::parse_from_utf8;
@@ -335,6 +428,134 @@ Accept-Language: en, mi
         ));
     }
 
+    #[test]
+    pub fn http_with_comments_and_leading_blank_lines() {
+        let http = r#"
+# get a single todo
+// with its author
+  GET https://jsonplaceholder.typicode.com/todos/1
+# the api only speaks json
+Accept: application/json
+// Accept-Language: en
+
+# the body keeps its comments
+"#;
+        let result = self::parse_from_utf8(http).unwrap();
+        assert_eq!(result.get_method(), "GET");
+        assert_eq!(
+            result.get_url(),
+            "https://jsonplaceholder.typicode.com/todos/1"
+        );
+        assert_eq!(result.get_header("Accept"), Some("application/json"));
+        assert_eq!(result.get_header("Accept-Language"), None);
+        assert_eq!(
+            result.into_body(),
+            Some(String::from("# the body keeps its comments"))
+        );
+    }
+
+    #[test]
+    pub fn http_errors_report_rows_after_comments() {
+        let http = r#"# comment
+
+GET https://jsonplaceholder.typicode.com/todos/1
+// comment
+Content-Type application/json
+"#;
+        assert!(matches!(
+            self::parse_from_utf8(http),
+            Err(HttpLexerError::MalformedLine {
+                row: 5,
+                col: 1,
+                content: _,
+                reason: _
+            })
+        ));
+
+        assert!(matches!(
+            self::parse_from_utf8("# only a comment\n\n"),
+            Err(HttpLexerError::MalformedHttpFile(_))
+        ));
+        assert!(matches!(
+            self::parse_from_utf8("\n   GET"),
+            Err(HttpLexerError::MalformedLine {
+                row: 2,
+                col: 4,
+                content: _,
+                reason: _
+            })
+        ));
+    }
+
+    #[test]
+    pub fn http_editor_dialect() {
+        let http = r#"@token = abc
+GET https://jsonplaceholder.typicode.com/comments HTTP/1.1
+    ?postId=1
+    &sort=desc
+Accept: application/json
+"#;
+        let result = self::parse_from_utf8(http).unwrap();
+        assert_eq!(
+            result.get_url(),
+            "https://jsonplaceholder.typicode.com/comments?postId=1&sort=desc"
+        );
+        assert_eq!(result.get_header("Accept"), Some("application/json"));
+
+        assert!(matches!(
+            self::parse_from_utf8("GET https://example.com HTTP/1.1 extra"),
+            Err(HttpLexerError::MalformedLine { row: 1, .. })
+        ));
+    }
+
+    #[test]
+    pub fn http_body_is_included_from_a_file() {
+        let hydrate = |text: &str| text.replace("{{userId}}", "7");
+
+        let http = "POST https://jsonplaceholder.typicode.com/posts\n\n< ./test.http";
+        let result = self::request(http, Path::new("src/http_file"), &hydrate).unwrap();
+        assert_eq!(
+            result.get_body(),
+            Some(&Body::File(Path::new("src/http_file").join("./test.http")))
+        );
+
+        let http = "POST https://jsonplaceholder.typicode.com/posts\n\n<@ ./test.http";
+        let result = self::request(http, Path::new("src/http_file"), &hydrate).unwrap();
+        let expected = std::fs::read_to_string("src/http_file/test.http").unwrap();
+        assert_eq!(result.into_body(), Some(hydrate(&expected)));
+
+        assert!(matches!(
+            self::request(http, Path::new("missing"), &hydrate),
+            Err(HttpLexerError::Include { .. })
+        ));
+    }
+
+    #[test]
+    pub fn http_multipart_body_gets_a_new_boundary() {
+        let http = r#"POST https://example.com/avatar
+Content-Type: multipart/form-data; boundary=WebAppBoundary
+
+--WebAppBoundary
+Content-Disposition: form-data; name="avatar"; filename="test.http"
+
+< ./test.http
+--WebAppBoundary--"#;
+        let result = self::request(http, Path::new("src/http_file"), &|t| t.to_string()).unwrap();
+
+        let Some(Body::Multipart(multipart)) = result.get_body() else {
+            panic!("body is not multipart");
+        };
+        assert_eq!(
+            result.get_header("content-type"),
+            Some(multipart.content_type().as_str())
+        );
+
+        let encoded = String::from_utf8(multipart.encode().unwrap()).unwrap();
+        let expected = std::fs::read_to_string("src/http_file/test.http").unwrap();
+        assert!(encoded.contains(&expected));
+        assert!(!encoded.contains("WebAppBoundary"));
+    }
+
     #[test]
     pub fn throws_error_on_missing_url() {
         let http = r#"GET "#;
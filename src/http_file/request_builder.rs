@@ -1,10 +1,61 @@
-use super::endpoint::{Endpoint, EndpointError};
+use super::{
+    endpoint::{Endpoint, EndpointError},
+    Multipart,
+};
+use crate::{auth::AuthConfig, signing::SigningConfig};
+use std::{borrow::Cow, path::PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    Text(String),
+    /// `< path`, streamed from the file as is when the request is sent
+    File(PathBuf),
+    /// `multipart/form-data`, encoded when the request is sent
+    Multipart(Multipart),
+}
+
+impl Body {
+    /// the whole body, a file is read into memory
+    pub fn bytes(&self) -> std::io::Result<Cow<'_, [u8]>> {
+        match self {
+            Body::Text(text) => Ok(Cow::Borrowed(text.as_bytes())),
+            Body::File(path) => std::fs::read(path).map(Cow::Owned),
+            Body::Multipart(multipart) => multipart.encode().map(Cow::Owned),
+        }
+    }
+
+    /// the error is boxed, `ureq::Error` holds a whole response
+    pub fn send(&self, request: ureq::Request) -> Result<ureq::Response, Box<ureq::Error>> {
+        match self {
+            Body::Text(text) => request.send_string(text).map_err(Box::new),
+            Body::File(path) => {
+                let file = std::fs::File::open(path).map_err(|e| Box::new(e.into()))?;
+                // without a length the body is sent chunked, which some servers reject
+                let request = match (request.has("content-length"), file.metadata()) {
+                    (false, Ok(metadata)) => {
+                        request.set("Content-Length", &metadata.len().to_string())
+                    }
+                    _ => request,
+                };
+                request.send(file).map_err(Box::new)
+            }
+            Body::Multipart(multipart) => {
+                let bytes = multipart.encode().map_err(|e| Box::new(e.into()))?;
+                request.send_bytes(&bytes).map_err(Box::new)
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct RequestBuilder {
     endpoint: Endpoint,
-    body: Option<String>,
+    body: Option<Body>,
     inner: http::request::Builder,
+    /// digest credentials, sent once the server answers with a challenge
+    auth: Option<AuthConfig>,
+    /// applied once every header is set
+    sign: Option<SigningConfig>,
 }
 
 impl RequestBuilder {
@@ -17,10 +68,11 @@ impl RequestBuilder {
             endpoint,
             body: None,
             inner: http::request::Builder::new(),
+            auth: None,
+            sign: None,
         }
     }
 
-    #[allow(dead_code)]
     pub fn get_header(&self, key: &str) -> Option<&str> {
         self.inner
             .headers_ref()
@@ -28,7 +80,6 @@ impl RequestBuilder {
             .and_then(|v| v.to_str().ok())
     }
 
-    #[allow(dead_code)]
     pub fn get_url(&self) -> &str {
         self.endpoint.get_url_as_str()
     }
@@ -42,10 +93,52 @@ impl RequestBuilder {
         self
     }
 
-    pub fn add_body(&mut self, body: String) {
+    /// basic and bearer credentials become the `Authorization` header unless the request already has one
+    pub fn auth(self, auth: AuthConfig) -> Self {
+        if self.get_header("authorization").is_some() {
+            return self;
+        }
+
+        match auth.header() {
+            Some(header) => self.add_header("Authorization", &header),
+            None => Self {
+                auth: Some(auth),
+                ..self
+            },
+        }
+    }
+
+    pub fn get_auth(&self) -> Option<&AuthConfig> {
+        self.auth.as_ref()
+    }
+
+    pub fn sign(self, sign: SigningConfig) -> Self {
+        Self {
+            sign: Some(sign),
+            ..self
+        }
+    }
+
+    pub fn get_sign(&self) -> Option<&SigningConfig> {
+        self.sign.as_ref()
+    }
+
+    /// a multipart body replaces the `Content-Type` header, its boundary is generated
+    pub fn add_body(&mut self, body: Body) {
+        if let Body::Multipart(multipart) = &body {
+            let content_type = http::HeaderValue::from_str(&multipart.content_type());
+            if let (Some(headers), Ok(content_type)) = (self.inner.headers_mut(), content_type) {
+                headers.insert(http::header::CONTENT_TYPE, content_type);
+            }
+        }
         self.body = Some(body);
     }
 
+    #[allow(dead_code)]
+    pub fn get_body(&self) -> Option<&Body> {
+        self.body.as_ref()
+    }
+
     #[allow(dead_code)]
     pub fn has_body(&self) -> bool {
         self.body.is_some()
@@ -61,10 +154,13 @@ impl RequestBuilder {
 
     #[allow(dead_code)]
     pub fn into_body(self) -> Option<String> {
-        self.body
+        match self.body? {
+            Body::Text(text) => Some(text),
+            Body::File(_) | Body::Multipart(_) => None,
+        }
     }
 
-    pub fn split(self) -> (http::request::Builder, Endpoint, Option<String>) {
+    pub fn split(self) -> (http::request::Builder, Endpoint, Option<Body>) {
         (self.inner, self.endpoint, self.body)
     }
 
@@ -121,8 +217,30 @@ impl RequestBuilder {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::test::server;
     use std::borrow::Cow;
 
+    #[test]
+    pub fn file_body_is_sent_as_is() -> anyhow::Result<()> {
+        let bytes = [0x89, b'P', b'N', b'G', 0x00, 0xff, 0x0a];
+        let path = std::env::temp_dir().join(format!("hat-body-{}.bin", std::process::id()));
+        std::fs::write(&path, bytes)?;
+
+        let (port, server) = server::serve(vec![server::response("200 OK", &[], "")]);
+
+        let request = ureq::post(&format!("http://127.0.0.1:{}/upload", port));
+        Body::File(path.clone())
+            .send(request)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        std::fs::remove_file(path)?;
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0].header("content-length"), Some("7"));
+        assert_eq!(requests[0].body, bytes);
+
+        Ok(())
+    }
+
     #[test]
     pub fn host_header_builder_works() -> anyhow::Result<()> {
         let https = url::Url::parse("https://google.com/something/api/blah")?;
@@ -0,0 +1,201 @@
+use crate::utc::Utc;
+use regex::{Captures, Regex};
+use std::{
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime},
+};
+
+lazy_static::lazy_static! {
+    static ref FILE_VARIABLE: Regex =
+        Regex::new(r"^\s*@([A-Za-z_][\w.-]*)\s*=\s*(.*?)\s*$").expect("pattern is invalid");
+    static ref SYSTEM_VARIABLE: Regex =
+        Regex::new(r"\{\{\s*\$([^}]*?)\s*}}").expect("pattern is invalid");
+}
+
+/// `@name = value`, a variable defined in a .http file
+pub fn is_file_variable(line: &str) -> bool {
+    FILE_VARIABLE.is_match(line)
+}
+
+/// the `@name = value` variables of a .http file, a value can use the variables above it
+pub fn file_variables(lines: &[&str]) -> Vec<(String, String)> {
+    let mut variables = Vec::<(String, String)>::new();
+
+    for line in lines {
+        if let Some(cap) = FILE_VARIABLE.captures(line) {
+            let value = self::replace(&cap[2], &variables);
+            variables.push((cap[1].to_string(), value));
+        }
+    }
+
+    variables
+}
+
+/// replaces `{{ name }}` with the file variables and `{{ $name }}` with the system variables,
+/// every other variable is left for the config's variables
+pub fn replace(contents: &str, variables: &[(String, String)]) -> String {
+    let mut contents = contents.to_string();
+
+    // later definitions win, like in an editor
+    for (name, value) in variables.iter().rev() {
+        let pattern = format!(r"\{{\{{\s*{}\s*}}}}", regex::escape(name));
+        let regex = Regex::new(&pattern).expect("pattern is invalid");
+        contents = regex
+            .replace_all(&contents, regex::NoExpand(value))
+            .into_owned();
+    }
+
+    SYSTEM_VARIABLE
+        .replace_all(&contents, |cap: &Captures| {
+            self::system(&cap[1]).unwrap_or_else(|| cap[0].to_string())
+        })
+        .into_owned()
+}
+
+/// the system variables of the VS Code REST Client and the JetBrains HTTP Client
+///
+/// `$guid`, `$uuid`, `$random.uuid`, `$randomInt [min max]`, `$timestamp [offset unit]`,
+/// `$isoTimestamp`, `$datetime rfc1123|iso8601 [offset unit]`, `$processEnv NAME`, and `$dotenv NAME`
+fn system(variable: &str) -> Option<String> {
+    let mut args = variable.split_whitespace();
+    let name = args.next()?;
+    let args: Vec<&str> = args.collect();
+
+    Some(match name {
+        "guid" | "uuid" | "random.uuid" => self::uuid(),
+        "randomInt" => {
+            let (min, max) = match args.as_slice() {
+                [min, max] => (min.parse::<i64>().ok()?, max.parse::<i64>().ok()?),
+                [] => (0, 1000),
+                _ => return None,
+            };
+            if max <= min {
+                return None;
+            }
+            (min + (self::random() % (max - min) as u64) as i64).to_string()
+        }
+        "timestamp" => {
+            let time = self::offset(SystemTime::now(), &args)?;
+            let seconds = time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            seconds.to_string()
+        }
+        "isoTimestamp" => Utc::from(SystemTime::now()).iso8601(),
+        "datetime" => {
+            let (format, offset) = args.split_first()?;
+            let time = self::offset(SystemTime::now(), offset)?;
+            match *format {
+                "iso8601" => Utc::from(time).iso8601(),
+                "rfc1123" => httpdate::fmt_http_date(time),
+                _ => return None,
+            }
+        }
+        "processEnv" | "dotenv" => dotenvy::var(args.first()?).ok()?,
+        _ => return None,
+    })
+}
+
+/// `-1 d`, `2 h`, or nothing, units are `ms`, `s`, `m`, `h`, `d`, and `w`
+fn offset(time: SystemTime, args: &[&str]) -> Option<SystemTime> {
+    let (amount, unit) = match args {
+        [] => return Some(time),
+        [amount, unit] => (amount.parse::<i64>().ok()?, *unit),
+        _ => return None,
+    };
+
+    let millis = match unit {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        "w" => 7 * 24 * 60 * 60 * 1000,
+        _ => return None,
+    };
+    let offset = Duration::from_millis(amount.unsigned_abs() * millis);
+
+    if amount < 0 {
+        time.checked_sub(offset)
+    } else {
+        time.checked_add(offset)
+    }
+}
+
+/// a random version 4 uuid
+pub fn uuid() -> String {
+    let (a, b) = (self::random(), self::random());
+    let a = (a & 0xffff_ffff_ffff_0fff) | 0x4000;
+    let b = (b & 0x3fff_ffff_ffff_ffff) | 0x8000_0000_0000_0000;
+
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        a >> 32,
+        (a >> 16) & 0xffff,
+        a & 0xffff,
+        b >> 48,
+        b & 0xffff_ffff_ffff
+    )
+}
+
+/// randomly seeded by the standard library, good enough for test data
+fn random() -> u64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn file_variables_are_replaced() {
+        let variables = file_variables(&[
+            "@host = localhost:5000",
+            "@base = http://{{host}}/api",
+            "# @name is not a variable",
+            "@ token =",
+        ]);
+        assert_eq!(
+            variables,
+            vec![
+                ("host".to_string(), "localhost:5000".to_string()),
+                ("base".to_string(), "http://localhost:5000/api".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            replace("GET {{ base }}/users/{{userId}}", &variables),
+            "GET http://localhost:5000/api/users/{{userId}}"
+        );
+    }
+
+    #[test]
+    pub fn system_variables_are_replaced() {
+        let uuid =
+            Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$")
+                .unwrap();
+        assert!(uuid.is_match(&replace("{{$guid}}", &[])));
+        assert!(uuid.is_match(&replace("{{ $random.uuid }}", &[])));
+
+        let number: i64 = replace("{{$randomInt 5 10}}", &[]).parse().unwrap();
+        assert!((5..10).contains(&number));
+
+        let now: u64 = replace("{{$timestamp}}", &[]).parse().unwrap();
+        let yesterday: u64 = replace("{{$timestamp -1 d}}", &[]).parse().unwrap();
+        assert!((86399..=86401).contains(&(now - yesterday)));
+
+        assert!(replace("{{$datetime rfc1123}}", &[]).ends_with(" GMT"));
+        assert!(replace("{{$datetime iso8601 1 h}}", &[]).ends_with('Z'));
+
+        // unknown system variables are left alone
+        assert_eq!(replace("{{$unknown}}", &[]), "{{$unknown}}");
+    }
+}
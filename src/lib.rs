@@ -1,26 +1,57 @@
 mod assertion;
+mod auth;
+mod client;
 mod config;
+mod cookies;
 mod error;
 mod factory;
 mod http_file;
-#[allow(dead_code)]
+mod oauth2;
 mod operator;
+mod poll;
+mod proxy;
 mod query;
+pub mod report;
+mod retry;
 mod runner;
+mod schema;
+mod signing;
+mod snapshot;
 mod store;
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod test;
+mod tls;
+mod utc;
 
 use clap::Parser;
 use query::Content;
-use runner::HatRunner;
+use report::{Format, Printer, ReportFile, TestOutcome, TestReport};
+use runner::{HatRunner, RunOptions};
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
-    /// path to .toml configuration file
-    path: String,
+    /// path(s) to .toml configuration file(s)
+    #[arg(required = true)]
+    path: Vec<String>,
+
+    /// how test results are printed to stdout
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// write a report file after all tests have run, e.g. junit=path/to/report.xml
+    #[arg(long, value_name = "FORMAT=PATH")]
+    report: Vec<ReportFile>,
+
+    /// write the response of every test with a `snapshot` to its snapshot file
+    #[arg(long)]
+    update_snapshots: bool,
+
+    /// run up to N tests at the same time, a test waits for the tests it depends on
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
 
     /// verbose level: DEBUG, INFO, ERROR
     #[arg(short, long, default_value_t = String::from("DEBUG"))]
@@ -29,10 +60,49 @@ pub struct Cli {
 
 pub fn start() -> anyhow::Result<bool> {
     let args = Cli::parse();
-    test(&args.path)
+
+    let mut printer = Printer::new(args.format);
+    printer.start();
+
+    let mut reports = Vec::new();
+    for path in &args.path {
+        printer.suite(path);
+        let options = RunOptions {
+            update_snapshots: args.update_snapshots,
+            jobs: args.jobs,
+        };
+        let report = match test(path, options, |i, outcome| {
+            printer.outcome(path, i, outcome)
+        }) {
+            Ok(report) => report,
+            // the other configs still run and every report is still written
+            Err(e) => {
+                let outcome = TestOutcome::failed(Some(path.to_string()), format!("{:#}", e));
+                printer.outcome(path, 0, &outcome);
+                TestReport {
+                    name: path.to_string(),
+                    tests: vec![outcome],
+                }
+            }
+        };
+        printer.report(&report);
+        reports.push(report);
+    }
+
+    printer.finish(&reports);
+
+    for r in &args.report {
+        r.write(&reports)?;
+    }
+
+    Ok(reports.iter().all(TestReport::passed))
 }
 
-fn test(config_path: &str) -> anyhow::Result<bool> {
+fn test<F: FnMut(usize, &TestOutcome)>(
+    config_path: &str,
+    options: RunOptions,
+    on_outcome: F,
+) -> anyhow::Result<TestReport> {
     let config = config::read(config_path)?;
 
     let environment = config
@@ -41,10 +111,18 @@ fn test(config_path: &str) -> anyhow::Result<bool> {
         .map(|(key, value)| (key, Content::new(value)))
         .collect();
 
-    let mut iter = config.tests.into_iter();
-    let mut runner = HatRunner::new(
+    let (auth, sign) = (config.auth, config.sign);
+    let mut iter = config
+        .tests
+        .into_iter()
+        .map(|t| t.or_auth(auth.as_ref()).or_sign(sign.as_ref()));
+    let runner = HatRunner::new(
         store::StoreUnion::MapStringToContent(environment),
-        ureq::AgentBuilder::new().build(),
+        client::Client::new(config.client),
+        options,
     );
-    Ok(runner.test(&mut iter))
+    let mut report = runner.test(&mut iter, on_outcome);
+    report.name = config_path.to_string();
+
+    Ok(report)
 }
@@ -0,0 +1,208 @@
+use crate::{error::HatError, store::Store};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// a token is fetched again when it expires within this margin
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// OAuth2 client credentials grant, RFC 6749 section 4.4
+///
+/// ```toml
+/// [auth.oauth2]
+/// token_url = "https://auth.example.com/oauth/token"
+/// client_id = "{{ clientId }}"
+/// client_secret = "{{ clientSecret }}"
+/// scopes = ["users:read"]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OAuth2Config {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl OAuth2Config {
+    pub fn hydrate<S: Store>(&self, store: &S) -> OAuth2Config {
+        let hydrate = |s: &String| store.match_and_replace(s, |v| v.as_value());
+
+        OAuth2Config {
+            token_url: hydrate(&self.token_url),
+            client_id: hydrate(&self.client_id),
+            client_secret: hydrate(&self.client_secret),
+            scopes: self.scopes.iter().map(hydrate).collect(),
+        }
+    }
+
+    pub fn values(&self) -> Vec<&str> {
+        [&self.token_url, &self.client_id, &self.client_secret]
+            .into_iter()
+            .chain(&self.scopes)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    access_token: String,
+    expires: Option<Instant>,
+    refresh_token: Option<String>,
+}
+
+impl Token {
+    fn expired(&self) -> bool {
+        self.expires
+            .is_some_and(|e| e <= Instant::now() + EXPIRY_MARGIN)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+/// tokens shared by every test in a run, one for each distinct `[auth]`
+#[derive(Debug, Default)]
+pub struct TokenCache {
+    tokens: Mutex<HashMap<OAuth2Config, Token>>,
+}
+
+impl TokenCache {
+    /// a cached access token, or a new one when it is missing or about to expire
+    ///
+    /// an expired token is refreshed with its refresh token when the server sent one
+    pub fn token(&self, config: &OAuth2Config, agent: &ureq::Agent) -> Result<String, HatError> {
+        // held while fetching, so tests running at the same time wait for a single request
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+
+        let refresh_token = match tokens.get(config) {
+            Some(t) if !t.expired() => return Ok(t.access_token.to_string()),
+            Some(t) => t.refresh_token.clone(),
+            None => None,
+        };
+
+        let refreshed = refresh_token.and_then(|r| {
+            self::fetch(
+                config,
+                agent,
+                &[("grant_type", "refresh_token"), ("refresh_token", &r)],
+            )
+            .map_err(|e| log::info!("refreshing the token failed, {}", e))
+            .ok()
+        });
+
+        let token = match refreshed {
+            Some(t) => t,
+            None => {
+                let scope = config.scopes.join(" ");
+                let mut form = vec![("grant_type", "client_credentials")];
+                if !scope.is_empty() {
+                    form.push(("scope", &scope));
+                }
+                self::fetch(config, agent, &form)?
+            }
+        };
+
+        tokens.insert(config.clone(), token.clone());
+        Ok(token.access_token)
+    }
+}
+
+/// the client authenticates with `client_secret_basic`
+fn fetch(
+    config: &OAuth2Config,
+    agent: &ureq::Agent,
+    form: &[(&str, &str)],
+) -> Result<Token, HatError> {
+    let credentials = crate::auth::basic(&config.client_id, &config.client_secret);
+
+    let response = agent
+        .post(&config.token_url)
+        .set("Authorization", &credentials)
+        .set("Accept", "application/json")
+        .send_form(form)
+        .map_err(|e| HatError::Auth(format!("{}: {}", config.token_url, e)))?;
+    let response: TokenResponse = serde_json::from_reader(response.into_reader())
+        .map_err(|e| HatError::Auth(format!("{}: {}", config.token_url, e)))?;
+
+    Ok(Token {
+        access_token: response.access_token,
+        expires: response
+            .expires_in
+            .map(|s| Instant::now() + Duration::from_secs(s)),
+        refresh_token: response.refresh_token,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::server::{self, Received};
+
+    /// answers each token request with the next response body
+    fn token_server(
+        responses: Vec<&'static str>,
+    ) -> (String, std::thread::JoinHandle<Vec<Received>>) {
+        let responses = responses
+            .into_iter()
+            .map(|body| server::response("200 OK", &["Content-Type: application/json"], body))
+            .collect();
+        let (port, server) = server::serve(responses);
+        (format!("http://127.0.0.1:{}/token", port), server)
+    }
+
+    fn config(token_url: String) -> OAuth2Config {
+        OAuth2Config {
+            token_url,
+            client_id: "hat".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: vec!["users:read".to_string(), "users:write".to_string()],
+        }
+    }
+
+    #[test]
+    fn token_is_cached() {
+        let (url, server) = token_server(vec![r#"{ "access_token": "t1", "expires_in": 3600 }"#]);
+        let config = config(url);
+        let cache = TokenCache::default();
+        let agent = ureq::Agent::new();
+
+        assert_eq!(cache.token(&config, &agent).unwrap(), "t1");
+        assert_eq!(cache.token(&config, &agent).unwrap(), "t1");
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].body,
+            b"grant_type=client_credentials&scope=users%3Aread+users%3Awrite"
+        );
+    }
+
+    #[test]
+    fn expired_token_is_refreshed() {
+        let (url, server) = token_server(vec![
+            r#"{ "access_token": "t1", "expires_in": 0, "refresh_token": "r1" }"#,
+            r#"{ "access_token": "t2", "expires_in": 3600 }"#,
+        ]);
+        let config = config(url);
+        let cache = TokenCache::default();
+        let agent = ureq::Agent::new();
+
+        assert_eq!(cache.token(&config, &agent).unwrap(), "t1");
+        assert_eq!(cache.token(&config, &agent).unwrap(), "t2");
+
+        let requests = server.join().unwrap();
+        assert_eq!(
+            requests[1].body,
+            b"grant_type=refresh_token&refresh_token=r1"
+        );
+    }
+}
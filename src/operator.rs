@@ -1,29 +1,57 @@
+use crate::{
+    query::Variable,
+    report::{AssertionOutcome, Substitution},
+    store::Store,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
 pub enum BinaryOperator {
     Equal,
+    NotEqual,
     GreaterThan,
     LessThan,
     GreaterThanOrEqualTo,
     LessThanOrEqualTo,
+    Contains,
+    Matches,
+    In,
+    Exists,
+    Type,
 }
 
 impl BinaryOperator {
     pub fn as_str(&self) -> &'static str {
         match &self {
             BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
             BinaryOperator::GreaterThan => ">",
             BinaryOperator::LessThan => "<",
             BinaryOperator::GreaterThanOrEqualTo => ">=",
             BinaryOperator::LessThanOrEqualTo => "<=",
+            BinaryOperator::Contains => "contains",
+            BinaryOperator::Matches => "matches",
+            BinaryOperator::In => "in",
+            BinaryOperator::Exists => "exists",
+            BinaryOperator::Type => "type",
         }
     }
 
     pub fn from_str(operator: &str) -> Option<Self> {
         match operator {
             "==" => Some(BinaryOperator::Equal),
+            "!=" => Some(BinaryOperator::NotEqual),
             ">" => Some(BinaryOperator::GreaterThan),
             "<" => Some(BinaryOperator::LessThan),
             ">=" => Some(BinaryOperator::GreaterThanOrEqualTo),
             "<=" => Some(BinaryOperator::LessThanOrEqualTo),
+            "contains" => Some(BinaryOperator::Contains),
+            "matches" => Some(BinaryOperator::Matches),
+            "in" => Some(BinaryOperator::In),
+            "exists" => Some(BinaryOperator::Exists),
+            "type" => Some(BinaryOperator::Type),
             _ => None,
         }
     }
@@ -32,17 +60,309 @@ impl BinaryOperator {
         format!("{} {} {}", left.as_ref(), self.as_str(), right.as_ref())
     }
 
-    pub fn execute<T: PartialEq<Q> + PartialOrd<Q>, Q: PartialEq<T> + PartialOrd<T>>(
-        &self,
-        left: T,
-        right: Q,
-    ) -> bool {
-        match &self {
-            BinaryOperator::Equal => left == right,
-            BinaryOperator::GreaterThan => left > right,
-            BinaryOperator::LessThan => left < right,
-            BinaryOperator::GreaterThanOrEqualTo => left >= right,
-            BinaryOperator::LessThanOrEqualTo => left <= right,
+    /// `actual` is `None` when the query did not match anything in the response
+    pub fn execute(&self, actual: Option<&Value>, expected: &Value) -> Result<bool, String> {
+        match self {
+            BinaryOperator::Exists => {
+                let expected = expected.as_bool().ok_or_else(|| {
+                    format!("'exists' expects true or false, but got {}", expected)
+                })?;
+                return Ok(actual.is_some() == expected);
+            }
+            BinaryOperator::Type => {
+                let expected = expected
+                    .as_str()
+                    .ok_or_else(|| format!("'type' expects a type name, but got {}", expected))?;
+                return Ok(actual.map(kind).unwrap_or("undefined") == expected);
+            }
+            _ => {}
+        }
+
+        let actual = actual.ok_or("value was not found")?;
+
+        Ok(match self {
+            BinaryOperator::Equal => equal(actual, expected),
+            BinaryOperator::NotEqual => !equal(actual, expected),
+            BinaryOperator::GreaterThan => compare(actual, expected)?.is_gt(),
+            BinaryOperator::LessThan => compare(actual, expected)?.is_lt(),
+            BinaryOperator::GreaterThanOrEqualTo => compare(actual, expected)?.is_ge(),
+            BinaryOperator::LessThanOrEqualTo => compare(actual, expected)?.is_le(),
+            BinaryOperator::Contains => contains(actual, expected)?,
+            BinaryOperator::In => contains(expected, actual)?,
+            BinaryOperator::Matches => {
+                let (Value::String(text), Value::String(pattern)) = (actual, expected) else {
+                    return Err(format!(
+                        "'matches' expects a string and a pattern, but got {} and {}",
+                        kind(actual),
+                        kind(expected)
+                    ));
+                };
+                regex::Regex::new(pattern)
+                    .map_err(|e| e.to_string())?
+                    .is_match(text)
+            }
+            BinaryOperator::Exists | BinaryOperator::Type => unreachable!(),
+        })
+    }
+}
+
+impl TryFrom<String> for BinaryOperator {
+    type Error = String;
+
+    fn try_from(operator: String) -> Result<Self, Self::Error> {
+        Self::from_str(&operator)
+            .ok_or_else(|| format!("'{}' is not a supported operator", operator))
+    }
+}
+
+impl From<BinaryOperator> for String {
+    fn from(operator: BinaryOperator) -> Self {
+        operator.as_str().to_string()
+    }
+}
+
+/// structured alternative to an assertion line
+///
+/// ```toml
+/// [[tests.expect]]
+/// query = "body | id"
+/// op = "=="
+/// value = 2
+/// ```
+///
+/// `value` can only be left out for `exists`, which then checks the query matched something
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(try_from = "ExpectationConfig")]
+pub struct Expectation {
+    query: String,
+    op: BinaryOperator,
+    value: Value,
+}
+
+#[derive(Deserialize)]
+struct ExpectationConfig {
+    query: String,
+    op: BinaryOperator,
+    value: Option<Value>,
+}
+
+impl TryFrom<ExpectationConfig> for Expectation {
+    type Error = String;
+
+    fn try_from(config: ExpectationConfig) -> Result<Self, Self::Error> {
+        let value = match (config.op, config.value) {
+            (_, Some(value)) => value,
+            // `exists` reads naturally without a value
+            (BinaryOperator::Exists, None) => Value::Bool(true),
+            (op, None) => {
+                return Err(format!(
+                    "'{}' needs a value to compare '{}' with",
+                    op.as_str(),
+                    config.query
+                ))
+            }
+        };
+
+        Ok(Self {
+            query: config.query,
+            op: config.op,
+            value,
+        })
+    }
+}
+
+impl Expectation {
+    /// the variable the query reads and the `{{ variables }}` in a string value
+    pub fn variables(&self) -> Vec<&str> {
+        let query = self.query.split('|').next().unwrap_or_default().trim();
+        let value = match &self.value {
+            Value::String(value) => crate::store::variables(value).collect(),
+            _ => Vec::new(),
+        };
+
+        std::iter::once(query).chain(value).collect()
+    }
+
+    pub fn assert<S: Store>(&self, store: &S) -> AssertionOutcome {
+        let variable = store.fetch_value(&self.query);
+        let actual = variable.as_ref().and_then(Variable::to_json);
+        // a string value can compare with an earlier test's output, e.g. "{{ userId }}"
+        let expected = match &self.value {
+            Value::String(value) => Value::String(store.match_and_replace(value, |v| v.as_value())),
+            value => value.clone(),
+        };
+        let result = self.op.execute(actual.as_ref(), &expected);
+
+        let actual_str = actual
+            .as_ref()
+            .map(|a| a.to_string())
+            .unwrap_or("undefined".to_string());
+
+        AssertionOutcome {
+            expression: self.op.print(self.query.trim(), expected.to_string()),
+            hydrated: self.op.print(&actual_str, expected.to_string()),
+            passed: result == Ok(true),
+            variables: vec![Substitution {
+                name: self.query.trim().to_string(),
+                kind: actual.as_ref().map(kind).unwrap_or("missing"),
+                value: actual.map(|_| actual_str),
+            }],
+            error: result.err(),
         }
     }
 }
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => l == r,
+        _ => left == right,
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Result<std::cmp::Ordering, String> {
+    let ordering = match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.as_f64().partial_cmp(&r.as_f64()),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        _ => None,
+    };
+
+    ordering.ok_or_else(|| format!("cannot compare {} with {}", kind(left), kind(right)))
+}
+
+fn contains(haystack: &Value, needle: &Value) -> Result<bool, String> {
+    match (haystack, needle) {
+        (Value::String(h), Value::String(n)) => Ok(h.contains(n.as_str())),
+        (Value::Array(h), n) => Ok(h.iter().any(|v| equal(v, n))),
+        (Value::Object(h), Value::String(n)) => Ok(h.contains_key(n)),
+        _ => Err(format!(
+            "{} cannot contain {}",
+            kind(haystack),
+            kind(needle)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        query::Content,
+        store::{ContentMap, StoreUnion},
+    };
+    use serde_json::json;
+
+    fn store() -> StoreUnion {
+        let mut map = ContentMap::new();
+        map.insert(
+            "body".to_string(),
+            Content::new(
+                r#"{ "id": 2, "message": "hello world", "tags": ["a", "b"], "user": { "name": "hat" } }"#
+                    .to_string(),
+            ),
+        );
+        StoreUnion::MapStringToContent(map)
+    }
+
+    fn expect(query: &str, op: &str, value: Value) -> AssertionOutcome {
+        Expectation {
+            query: query.to_string(),
+            op: BinaryOperator::from_str(op).unwrap(),
+            value,
+        }
+        .assert(&store())
+    }
+
+    #[test]
+    fn typed_comparisons() {
+        assert!(expect("body | id", "==", json!(2)).passed);
+        assert!(expect("body | id", "==", json!(2.0)).passed);
+        assert!(!expect("body | id", "==", json!("2")).passed);
+        assert!(expect("body | id", "!=", json!(3)).passed);
+        assert!(expect("body | id", ">=", json!(2)).passed);
+        assert!(expect("body | message", "<", json!("zzz")).passed);
+    }
+
+    #[test]
+    fn collection_operators() {
+        assert!(expect("body | message", "contains", json!("world")).passed);
+        assert!(expect("body | tags", "contains", json!("b")).passed);
+        assert!(expect("body | user", "contains", json!("name")).passed);
+        assert!(expect("body | id", "in", json!([1, 2, 3])).passed);
+        assert!(expect("body | message", "matches", json!("^hello \\w+$")).passed);
+    }
+
+    #[test]
+    fn existence_and_type() {
+        assert!(expect("body | id", "exists", json!(true)).passed);
+        assert!(expect("body | nope", "exists", json!(false)).passed);
+        assert!(expect("body | tags", "type", json!("array")).passed);
+        assert!(expect("body | nope", "type", json!("undefined")).passed);
+    }
+
+    #[test]
+    fn failures_explain_themselves() {
+        let outcome = expect("body | id", "==", json!(3));
+        assert!(!outcome.passed);
+        assert_eq!(outcome.expression, "body | id == 3");
+        assert_eq!(outcome.hydrated, "2 == 3");
+        assert_eq!(outcome.variables[0].kind, "number");
+        assert_eq!(outcome.error, None);
+
+        let outcome = expect("body | message", ">", json!(2));
+        assert_eq!(
+            outcome.error.as_deref(),
+            Some("cannot compare string with number")
+        );
+
+        let outcome = expect("body | nope", "==", json!(2));
+        assert_eq!(outcome.hydrated, "undefined == 2");
+        assert_eq!(outcome.error.as_deref(), Some("value was not found"));
+    }
+
+    #[test]
+    fn operators_deserialize_from_strings() {
+        let expectation: Expectation =
+            toml::from_str("query = \"status\"\nop = \"matches\"\nvalue = \"^2\"").unwrap();
+        assert_eq!(expectation.op, BinaryOperator::Matches);
+        assert!(toml::from_str::<Expectation>("query = \"status\"\nop = \"~=\"").is_err());
+    }
+
+    #[test]
+    fn string_values_are_hydrated() {
+        let mut map = ContentMap::new();
+        map.insert("name".to_string(), Content::new("hat".to_string()));
+        map.insert(
+            "body".to_string(),
+            Content::new(r#"{ "user": { "name": "hat" } }"#.to_string()),
+        );
+
+        let outcome = Expectation {
+            query: "body | user.name".to_string(),
+            op: BinaryOperator::Equal,
+            value: json!("{{ name }}"),
+        }
+        .assert(&StoreUnion::MapStringToContent(map));
+        assert!(outcome.passed);
+        assert_eq!(outcome.expression, r#"body | user.name == "hat""#);
+    }
+
+    #[test]
+    fn value_is_required_except_for_exists() {
+        let error = toml::from_str::<Expectation>("query = \"body | id\"\nop = \">\"").unwrap_err();
+        assert!(error.message().contains("'>' needs a value"));
+
+        let exists: Expectation = toml::from_str("query = \"body | id\"\nop = \"exists\"").unwrap();
+        assert!(exists.assert(&store()).passed);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    report::{AssertionOutcome, Substitution},
+    store::Store,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// ```toml
+/// [tests.poll]
+/// until = "{{ body | state }} == \"done\""
+/// interval = "1s"
+/// timeout = "30s"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PollConfig {
+    /// the request is sent again until this assertion passes
+    pub until: String,
+    #[serde(default = "default_interval", with = "humantime_serde")]
+    pub interval: Duration,
+    #[serde(default = "default_timeout", with = "humantime_serde")]
+    pub timeout: Duration,
+}
+
+fn default_interval() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+impl PollConfig {
+    /// evaluates `until` against the latest response
+    pub fn check<S: Store>(&self, store: &S) -> AssertionOutcome {
+        let (hydrated, variables) = store.match_and_capture(&self.until, |v| v.as_literal());
+        let result = crate::assertion::evaluate(&hydrated);
+
+        AssertionOutcome {
+            expression: format!("poll until {}", self.until),
+            passed: result == Ok(true),
+            hydrated,
+            variables: variables.into_iter().map(Substitution::from).collect(),
+            error: result.err(),
+        }
+    }
+
+    /// true when there is time for another request after waiting `interval`
+    pub fn can_continue(&self, started: Instant) -> bool {
+        started.elapsed() + self.interval <= self.timeout
+    }
+
+    /// the condition did not hold before `timeout`
+    pub fn timed_out(&self, mut outcome: AssertionOutcome, requests: usize) -> AssertionOutcome {
+        let timeout = format!(
+            "did not hold within {} after {} request(s)",
+            humantime_serde::re::humantime::format_duration(self.timeout),
+            requests
+        );
+
+        outcome.error = Some(match outcome.error {
+            Some(e) => format!("{}, {}", timeout, e),
+            None => timeout,
+        });
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        query::Content,
+        store::{ContentMap, StoreUnion},
+    };
+
+    fn store(state: &str) -> StoreUnion {
+        let mut map = ContentMap::new();
+        map.insert(
+            "body".to_string(),
+            Content::new(format!(r#"{{ "state": "{}" }}"#, state)),
+        );
+        StoreUnion::MapStringToContent(map)
+    }
+
+    #[test]
+    fn until_is_checked_against_the_response() {
+        let poll: PollConfig =
+            toml::from_str("until = '{{ body | state }} == \"done\"'\ninterval = \"250ms\"")
+                .unwrap();
+        assert_eq!(poll.interval, Duration::from_millis(250));
+        assert_eq!(poll.timeout, Duration::from_secs(30));
+
+        let outcome = poll.check(&store("running"));
+        assert!(!outcome.passed);
+        assert_eq!(outcome.hydrated, r#""running" == "done""#);
+
+        let outcome = poll.timed_out(outcome, 3);
+        assert_eq!(
+            outcome.error.as_deref(),
+            Some("did not hold within 30s after 3 request(s)")
+        );
+
+        assert!(poll.check(&store("done")).passed);
+    }
+}
@@ -15,10 +15,39 @@ impl Variable<'_> {
         .to_string()
     }
 
+    /// `None` when a json query did not match anything
+    pub fn to_json(&self) -> Option<serde_json::Value> {
+        match self {
+            Variable::Json(value) if !value.exists() => None,
+            Variable::Json(value) => serde_json::from_str(value.json()).ok(),
+            Variable::Text(text) => Some(serde_json::Value::String(text.to_string())),
+        }
+    }
+
+    /// type name shown in test output, e.g. `number`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Variable::Json(value) => match value.kind() {
+                gjson::Kind::Null => "null",
+                gjson::Kind::False | gjson::Kind::True => "boolean",
+                gjson::Kind::Number => "number",
+                gjson::Kind::String => "string",
+                gjson::Kind::Array => "array",
+                gjson::Kind::Object => "object",
+            },
+            Variable::Text(_) => "text",
+        }
+    }
+
+    /// the variable written as an assertion expression, a json array becomes a tuple
     pub fn as_literal(&self) -> String {
         match self {
             Variable::Json(value) => match value.kind() {
                 gjson::Kind::String => format!("\"{}\"", value.str()),
+                gjson::Kind::Array => match serde_json::from_str(value.json()) {
+                    Ok(array) => self::literal(&array),
+                    Err(_) => self.as_value(),
+                },
                 _ => self.as_value(),
             },
             Variable::Text(x) => format!("\"{}\"", x),
@@ -26,6 +55,26 @@ impl Variable<'_> {
     }
 }
 
+/// `["a", "b"]` is `("a", "b")`, the expression language has no syntax for a tuple with one
+/// or no items so those are written as `array("a")` and `array()`
+fn literal(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    match value {
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(self::literal).collect();
+            match items.len() {
+                0 | 1 => format!("array({})", items.join("")),
+                _ => format!("({})", items.join(", ")),
+            }
+        }
+        Value::Null => "()".to_string(),
+        // objects are compared as their json text
+        Value::Object(_) => Value::String(value.to_string()).to_string(),
+        _ => value.to_string(),
+    }
+}
+
 #[derive(Debug)]
 pub enum Content {
     Json(String),
@@ -45,6 +94,12 @@ impl Content {
         Content::Plaintext(content)
     }
 
+    pub fn as_str(&self) -> &str {
+        match self {
+            Content::Json(x) | Content::Xml(x) | Content::Plaintext(x) => x,
+        }
+    }
+
     // pass in arbitrary filter to extract data from body
     // e.g. Json -> filter = ".posts.[0]"
     // e.g. Plaintext -> filter = "/\w+/g"
@@ -0,0 +1,242 @@
+use super::{Substitution, TestOutcome, TestReport};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Start {
+        suite: &'a str,
+        test: usize,
+        description: Option<&'a str>,
+    },
+    /// an earlier attempt of a test with `retry`
+    Attempt {
+        suite: &'a str,
+        test: usize,
+        attempt: usize,
+        response: Option<String>,
+        failures: usize,
+        duration_ms: u128,
+        error: Option<&'a str>,
+    },
+    Request {
+        suite: &'a str,
+        test: usize,
+        method: &'a str,
+        request: &'a str,
+    },
+    Response {
+        suite: &'a str,
+        test: usize,
+        status: u16,
+        status_text: &'a str,
+        url: &'a str,
+        http_version: &'a str,
+        duration_ms: u128,
+        ttfb_ms: u128,
+    },
+    Assertion {
+        suite: &'a str,
+        test: usize,
+        expression: &'a str,
+        hydrated: &'a str,
+        passed: bool,
+        variables: &'a [Substitution],
+        error: Option<&'a str>,
+    },
+    Output {
+        suite: &'a str,
+        test: usize,
+        name: &'a str,
+        value: &'a str,
+    },
+    End {
+        suite: &'a str,
+        test: usize,
+        passed: bool,
+        duration_ms: u128,
+        error: Option<&'a str>,
+    },
+}
+
+/// events for the test at `index` in the order they happened
+pub fn events<'a>(suite: &'a str, index: usize, outcome: &'a TestOutcome) -> Vec<Event<'a>> {
+    let test = index + 1;
+    let duration_ms = outcome.duration.as_millis();
+
+    let mut events = vec![Event::Start {
+        suite,
+        test,
+        description: outcome.description.as_deref(),
+    }];
+
+    events.extend(
+        outcome
+            .attempts
+            .iter()
+            .enumerate()
+            .map(|(i, a)| Event::Attempt {
+                suite,
+                test,
+                attempt: i + 1,
+                response: a.response.as_ref().map(|r| r.to_string()),
+                failures: a.failures,
+                duration_ms: a.duration.as_millis(),
+                error: a.error.as_deref(),
+            }),
+    );
+
+    if let Some(r) = &outcome.request {
+        events.push(Event::Request {
+            suite,
+            test,
+            method: r.split(' ').next().unwrap_or_default(),
+            request: r,
+        });
+    }
+
+    if let Some(r) = &outcome.response {
+        events.push(Event::Response {
+            suite,
+            test,
+            status: r.status,
+            status_text: &r.status_text,
+            url: &r.url,
+            http_version: &r.http_version,
+            duration_ms: r.timing.duration.as_millis(),
+            ttfb_ms: r.timing.ttfb.as_millis(),
+        });
+    }
+
+    events.extend(outcome.assertions.iter().map(|a| Event::Assertion {
+        suite,
+        test,
+        expression: &a.expression,
+        hydrated: &a.hydrated,
+        passed: a.passed,
+        variables: &a.variables,
+        error: a.error.as_deref(),
+    }));
+
+    events.extend(outcome.outputs.iter().map(|(name, value)| Event::Output {
+        suite,
+        test,
+        name,
+        value,
+    }));
+
+    events.push(Event::End {
+        suite,
+        test,
+        passed: outcome.passed(),
+        duration_ms,
+        error: outcome.error.as_deref(),
+    });
+
+    events
+}
+
+/// a single JSON array holding the events of every test
+pub fn render(reports: &[TestReport]) -> String {
+    let events: Vec<Event> = reports
+        .iter()
+        .flat_map(|r| {
+            r.tests
+                .iter()
+                .enumerate()
+                .flat_map(|(i, t)| self::events(&r.name, i, t))
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&events).expect("events are always serializable")
+}
+
+/// one JSON event per line for the test at `index`
+pub fn render_lines(suite: &str, index: usize, outcome: &TestOutcome) -> String {
+    self::events(suite, index, outcome)
+        .iter()
+        .map(|e| serde_json::to_string(e).expect("events are always serializable"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::{AssertionOutcome, ResponseSummary};
+
+    #[test]
+    fn renders_one_event_per_line() {
+        let outcome = TestOutcome {
+            description: Some("post message".to_string()),
+            request: Some("POST http://localhost:5000/message".to_string()),
+            response: Some(ResponseSummary::ok("POST", "http://localhost:5000/message")),
+            assertions: vec![AssertionOutcome {
+                expression: "{{body | id}} == 2".to_string(),
+                hydrated: "2 == 2".to_string(),
+                passed: true,
+                ..Default::default()
+            }],
+            outputs: vec![("messageId".to_string(), "2".to_string())],
+            ..Default::default()
+        };
+
+        let lines = render_lines("config.toml", 0, &outcome);
+        let lines: Vec<&str> = lines.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"event":"start","suite":"config.toml","test":1,"description":"post message"}"#,
+                r#"{"event":"request","suite":"config.toml","test":1,"method":"POST","request":"POST http://localhost:5000/message"}"#,
+                r#"{"event":"response","suite":"config.toml","test":1,"status":200,"status_text":"OK","url":"http://localhost:5000/message","http_version":"HTTP/1.1","duration_ms":0,"ttfb_ms":0}"#,
+                r#"{"event":"assertion","suite":"config.toml","test":1,"expression":"{{body | id}} == 2","hydrated":"2 == 2","passed":true,"variables":[],"error":null}"#,
+                r#"{"event":"output","suite":"config.toml","test":1,"name":"messageId","value":"2"}"#,
+                r#"{"event":"end","suite":"config.toml","test":1,"passed":true,"duration_ms":0,"error":null}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn attempts_follow_start() {
+        let outcome = TestOutcome {
+            attempts: vec![crate::report::Attempt {
+                error: Some("404 Not Found".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let events = events("config.toml", 0, &outcome);
+
+        assert_eq!(
+            events[1],
+            Event::Attempt {
+                suite: "config.toml",
+                test: 1,
+                attempt: 1,
+                response: None,
+                failures: 0,
+                duration_ms: 0,
+                error: Some("404 Not Found"),
+            }
+        );
+    }
+
+    #[test]
+    fn failed_test_only_has_start_and_end() {
+        let outcome = TestOutcome::failed(None, "connection refused".to_string());
+        let events = events("config.toml", 2, &outcome);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1],
+            Event::End {
+                suite: "config.toml",
+                test: 3,
+                passed: false,
+                duration_ms: 0,
+                error: Some("connection refused"),
+            }
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use super::{TestOutcome, TestReport};
+use std::fmt::Write;
+
+/// renders JUnit XML with one `<testsuite>` per config file and one `<testcase>` per test
+pub fn render(reports: &[TestReport]) -> String {
+    let tests: usize = reports.iter().map(|r| r.tests.len()).sum();
+    let failures: usize = reports.iter().map(TestReport::failures).sum();
+    let errors: usize = reports.iter().map(TestReport::errors).sum();
+    let time: f64 = reports.iter().map(|r| r.duration().as_secs_f64()).sum();
+
+    let mut buffer = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        buffer,
+        r#"<testsuites name="hat" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+        tests, failures, errors, time
+    );
+
+    for report in reports {
+        self::suite(&mut buffer, report);
+    }
+
+    buffer.push_str("</testsuites>\n");
+    buffer
+}
+
+fn suite(buffer: &mut String, report: &TestReport) {
+    let name = escape(&report.name);
+    let _ = writeln!(
+        buffer,
+        r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+        name,
+        report.tests.len(),
+        report.failures(),
+        report.errors(),
+        report.duration().as_secs_f64()
+    );
+
+    for (i, test) in report.tests.iter().enumerate() {
+        let _ = write!(
+            buffer,
+            r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+            escape(&test.name(i)),
+            name,
+            test.duration.as_secs_f64()
+        );
+
+        if test.passed() {
+            buffer.push_str("/>\n");
+            continue;
+        }
+
+        buffer.push_str(">\n");
+        self::failure(buffer, test);
+        buffer.push_str("    </testcase>\n");
+    }
+
+    buffer.push_str("  </testsuite>\n");
+}
+
+fn failure(buffer: &mut String, test: &TestOutcome) {
+    if let Some(e) = &test.error {
+        let _ = writeln!(
+            buffer,
+            r#"      <error message="{}" type="error">{}</error>"#,
+            escape(e.lines().next().unwrap_or_default()),
+            escape(e)
+        );
+        return;
+    }
+
+    let failed: Vec<_> = test.assertions.iter().filter(|a| !a.passed).collect();
+
+    let mut content = test
+        .response
+        .as_ref()
+        .map(|r| r.to_string())
+        .unwrap_or_default();
+    for (i, a) in test.attempts.iter().enumerate() {
+        content.push_str(&format!("\nattempt {}: {}", i + 1, a));
+    }
+    for f in &failed {
+        content.push('\n');
+        content.push_str(&f.hydrated);
+        for d in f.details() {
+            content.push_str("\n  ");
+            content.push_str(&d);
+        }
+    }
+
+    let _ = writeln!(
+        buffer,
+        r#"      <failure message="{} of {} assertion(s) failed" type="assertion">{}</failure>"#,
+        failed.len(),
+        test.assertions.len(),
+        escape(&content)
+    );
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::{AssertionOutcome, ResponseSummary};
+
+    #[test]
+    fn renders_suite_per_report_and_case_per_test() {
+        let report = TestReport {
+            name: "example/local/config.toml".to_string(),
+            tests: vec![
+                TestOutcome {
+                    description: Some("status only".to_string()),
+                    request: Some("GET http://localhost:5000/200".to_string()),
+                    response: Some(ResponseSummary::ok("GET", "http://localhost:5000/200")),
+                    assertions: vec![AssertionOutcome {
+                        expression: "{{status}} == 200".to_string(),
+                        hydrated: "200 == 200".to_string(),
+                        passed: true,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                TestOutcome {
+                    request: Some("GET http://localhost:5000/message".to_string()),
+                    response: Some(ResponseSummary::ok("GET", "http://localhost:5000/message")),
+                    assertions: vec![AssertionOutcome {
+                        expression: "{{body | id}} < 2".to_string(),
+                        hydrated: "2 < 2".to_string(),
+                        passed: false,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                TestOutcome::failed(None, "connection refused".to_string()),
+            ],
+        };
+
+        assert_eq!(
+            render(&[report]),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="hat" tests="3" failures="1" errors="1" time="0.000">
+  <testsuite name="example/local/config.toml" tests="3" failures="1" errors="1" time="0.000">
+    <testcase name="status only" classname="example/local/config.toml" time="0.000"/>
+    <testcase name="GET http://localhost:5000/message" classname="example/local/config.toml" time="0.000">
+      <failure message="1 of 1 assertion(s) failed" type="assertion">200 OK GET http://localhost:5000/message HTTP/1.1 in 0ms
+2 &lt; 2
+  {{body | id}} &lt; 2</failure>
+    </testcase>
+    <testcase name="test #3" classname="example/local/config.toml" time="0.000">
+      <error message="connection refused" type="error">connection refused</error>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
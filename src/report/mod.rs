@@ -0,0 +1,325 @@
+use serde::Serialize;
+use std::{fmt::Display, path::PathBuf, str::FromStr, time::Duration};
+
+pub mod json;
+pub mod junit;
+pub mod tap;
+pub mod text;
+
+/// how test results are printed to stdout
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Jsonl,
+    Tap,
+}
+
+/// prints test results to stdout in the requested [`Format`]
+pub struct Printer {
+    format: Format,
+    count: usize,
+}
+
+impl Printer {
+    pub fn new(format: Format) -> Self {
+        Self { format, count: 0 }
+    }
+
+    pub fn start(&mut self) {
+        if self.format == Format::Tap {
+            println!("{}", tap::VERSION);
+        }
+    }
+
+    pub fn suite(&mut self, name: &str) {
+        if self.format == Format::Tap {
+            println!("{}", tap::suite(name));
+        }
+    }
+
+    pub fn outcome(&mut self, suite: &str, index: usize, outcome: &TestOutcome) {
+        self.count += 1;
+
+        match self.format {
+            Format::Jsonl => println!("{}", json::render_lines(suite, index, outcome)),
+            Format::Tap => println!("{}", tap::test_point(self.count, index, outcome)),
+            Format::Text | Format::Json => {}
+        }
+    }
+
+    pub fn report(&mut self, report: &TestReport) {
+        if self.format == Format::Text {
+            println!("{}", text::render(report));
+        }
+    }
+
+    pub fn finish(&mut self, reports: &[TestReport]) {
+        match self.format {
+            Format::Json => println!("{}", json::render(reports)),
+            Format::Tap => println!("{}", tap::plan(self.count)),
+            Format::Text | Format::Jsonl => {}
+        }
+    }
+}
+
+/// report file requested from the cli, e.g. `--report junit=path/to/report.xml`
+#[derive(Debug, Clone)]
+pub enum ReportFile {
+    Junit(PathBuf),
+}
+
+impl FromStr for ReportFile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = s
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' is invalid, expected <FORMAT>=<PATH>", s))?;
+
+        if path.is_empty() {
+            return Err(format!("'{}' is missing a path", s));
+        }
+
+        match format {
+            "junit" => Ok(ReportFile::Junit(PathBuf::from(path))),
+            _ => Err(format!(
+                "'{}' is not a supported report format, expected: junit",
+                format
+            )),
+        }
+    }
+}
+
+impl ReportFile {
+    pub fn write(&self, reports: &[TestReport]) -> std::io::Result<()> {
+        match self {
+            ReportFile::Junit(path) => std::fs::write(path, junit::render(reports)),
+        }
+    }
+}
+
+/// results of every test executed from a single config file
+#[derive(Debug, Default)]
+pub struct TestReport {
+    /// path to the config file the tests were loaded from
+    pub name: String,
+    pub tests: Vec<TestOutcome>,
+}
+
+impl TestReport {
+    pub fn push(&mut self, outcome: TestOutcome) {
+        self.tests.push(outcome);
+    }
+
+    pub fn passed(&self) -> bool {
+        self.tests.iter().all(TestOutcome::passed)
+    }
+
+    pub fn failures(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| t.error.is_none() && !t.passed())
+            .count()
+    }
+
+    pub fn errors(&self) -> usize {
+        self.tests.iter().filter(|t| t.error.is_some()).count()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.tests.iter().map(|t| t.duration).sum()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TestOutcome {
+    pub description: Option<String>,
+    /// <METHOD> <URL>
+    pub request: Option<String>,
+    pub response: Option<ResponseSummary>,
+    pub assertions: Vec<AssertionOutcome>,
+    /// variables added to the global store by `[tests.outputs]`
+    pub outputs: Vec<(String, String)>,
+    /// earlier attempts of a test with `retry`, the last attempt is the outcome itself
+    pub attempts: Vec<Attempt>,
+    pub duration: Duration,
+    /// set when the test could not be built or executed
+    pub error: Option<String>,
+}
+
+impl TestOutcome {
+    pub fn failed(description: Option<String>, cause: String) -> Self {
+        Self {
+            description,
+            error: Some(cause),
+            ..Default::default()
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.assertions.iter().all(|a| a.passed)
+    }
+
+    /// description, falling back to the request line
+    pub fn name(&self, index: usize) -> String {
+        self.description
+            .as_ref()
+            .or(self.request.as_ref())
+            .cloned()
+            .unwrap_or_else(|| format!("test #{}", index + 1))
+    }
+}
+
+/// an attempt that was followed by another attempt
+#[derive(Debug, Default)]
+pub struct Attempt {
+    pub response: Option<ResponseSummary>,
+    pub assertions: usize,
+    pub failures: usize,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+impl From<TestOutcome> for Attempt {
+    fn from(outcome: TestOutcome) -> Self {
+        Self {
+            assertions: outcome.assertions.len(),
+            failures: outcome.assertions.iter().filter(|a| !a.passed).count(),
+            response: outcome.response,
+            duration: outcome.duration,
+            error: outcome.error,
+        }
+    }
+}
+
+/// <RESPONSE>, <FAILURES> of <ASSERTIONS> assertion(s) failed OR <ERROR>
+impl Display for Attempt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.error, &self.response) {
+            (Some(e), _) => write!(f, "{}", e),
+            (None, Some(r)) => write!(
+                f,
+                "{}, {} of {} assertion(s) failed",
+                r, self.failures, self.assertions
+            ),
+            (None, None) => write!(
+                f,
+                "{} of {} assertion(s) failed",
+                self.failures, self.assertions
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseSummary {
+    pub status: u16,
+    pub status_text: String,
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub timing: Timing,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timing {
+    /// from sending the request until the response body was read
+    pub duration: Duration,
+    /// from sending the request until the response headers were read
+    pub ttfb: Duration,
+}
+
+impl ResponseSummary {
+    pub fn new(method: &str, response: &ureq::Response) -> Self {
+        Self {
+            status: response.status(),
+            status_text: response.status_text().to_string(),
+            method: method.to_string(),
+            url: response.get_url().to_string(),
+            http_version: response.http_version().to_string(),
+            timing: Timing::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn ok(method: &str, url: &str) -> Self {
+        Self {
+            status: 200,
+            status_text: "OK".to_string(),
+            method: method.to_string(),
+            url: url.to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            timing: Timing::default(),
+        }
+    }
+}
+
+/// <STATUS> <STATUS_TEXT> <METHOD> <URL> <HTTP_VERSION> in <DURATION>ms
+impl Display for ResponseSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} in {}ms",
+            self.status,
+            self.status_text,
+            self.method,
+            self.url,
+            self.http_version,
+            self.timing.duration.as_millis()
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AssertionOutcome {
+    /// assertion as written in the config, e.g. `{{ status }} == 200`
+    pub expression: String,
+    /// assertion after variables were replaced, e.g. `200 == 200`
+    pub hydrated: String,
+    pub passed: bool,
+    /// variables replaced in the expression
+    pub variables: Vec<Substitution>,
+    /// set when the expression could not be evaluated, as opposed to evaluating to false
+    pub error: Option<String>,
+}
+
+impl AssertionOutcome {
+    /// lines explaining why the assertion failed
+    pub fn details(&self) -> Vec<String> {
+        let mut details = vec![self.expression.to_string()];
+
+        for v in &self.variables {
+            details.push(match &v.value {
+                Some(value) => format!("{} = {} ({})", v.name, value, v.kind),
+                None => format!("{} was not found", v.name),
+            });
+        }
+
+        if let Some(e) = &self.error {
+            details.push(format!("error: {}", e));
+        }
+
+        details
+    }
+}
+
+/// a `{{ <name> }}` variable and the value it was replaced with
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Substitution {
+    pub name: String,
+    /// `None` when the variable could not be found
+    pub value: Option<String>,
+    pub kind: &'static str,
+}
+
+impl From<crate::store::Capture> for Substitution {
+    fn from(capture: crate::store::Capture) -> Self {
+        Self {
+            name: capture.name,
+            value: capture.value,
+            kind: capture.kind,
+        }
+    }
+}
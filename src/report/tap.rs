@@ -0,0 +1,161 @@
+use super::TestOutcome;
+use std::fmt::Write;
+
+pub const VERSION: &str = "TAP version 13";
+
+pub fn suite(name: &str) -> String {
+    format!("# {}", name)
+}
+
+pub fn plan(count: usize) -> String {
+    format!("1..{}", count)
+}
+
+/// `ok <NUMBER> - <NAME>`, followed by a YAML diagnostic block when the test failed
+pub fn test_point(number: usize, index: usize, outcome: &TestOutcome) -> String {
+    let passed = outcome.passed();
+    let mut buffer = format!(
+        "{} {} - {}",
+        if passed { "ok" } else { "not ok" },
+        number,
+        outcome.name(index)
+    );
+
+    if passed {
+        return buffer;
+    }
+
+    buffer.push_str("\n  ---");
+
+    if let Some(e) = &outcome.error {
+        let _ = write!(buffer, "\n  error: {}", yaml(e));
+    }
+
+    if let Some(r) = &outcome.response {
+        let _ = write!(buffer, "\n  response: {}", yaml(&r.to_string()));
+    }
+
+    if !outcome.attempts.is_empty() {
+        buffer.push_str("\n  attempts:");
+        for a in &outcome.attempts {
+            let _ = write!(buffer, "\n    - {}", yaml(&a.to_string()));
+        }
+    }
+
+    let failures: Vec<_> = outcome.assertions.iter().filter(|a| !a.passed).collect();
+    if !failures.is_empty() {
+        buffer.push_str("\n  failures:");
+        for a in failures {
+            let _ = write!(
+                buffer,
+                "\n    - expression: {}\n      hydrated: {}",
+                yaml(&a.expression),
+                yaml(&a.hydrated)
+            );
+
+            if !a.variables.is_empty() {
+                buffer.push_str("\n      variables:");
+                for v in &a.variables {
+                    let _ = write!(
+                        buffer,
+                        "\n        - name: {}\n          value: {}\n          kind: {}",
+                        yaml(&v.name),
+                        v.value.as_deref().map(yaml).unwrap_or("null".to_string()),
+                        v.kind
+                    );
+                }
+            }
+
+            if let Some(e) = &a.error {
+                let _ = write!(buffer, "\n      error: {}", yaml(e));
+            }
+        }
+    }
+
+    let _ = write!(
+        buffer,
+        "\n  duration_ms: {}\n  ...",
+        outcome.duration.as_millis()
+    );
+
+    buffer
+}
+
+/// JSON strings are valid YAML double-quoted scalars
+fn yaml(value: &str) -> String {
+    serde_json::to_string(value).expect("strings are always serializable")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::{AssertionOutcome, ResponseSummary, Substitution, TestReport};
+
+    #[test]
+    fn failed_assertions_are_in_the_diagnostic_block() {
+        let report = TestReport {
+            name: "config.toml".to_string(),
+            tests: vec![
+                TestOutcome {
+                    description: Some("status only".to_string()),
+                    ..Default::default()
+                },
+                TestOutcome {
+                    description: Some("post message".to_string()),
+                    response: Some(ResponseSummary::ok("POST", "http://localhost:5000/message")),
+                    assertions: vec![
+                        AssertionOutcome {
+                            expression: "{{status}} == 200".to_string(),
+                            hydrated: "200 == 200".to_string(),
+                            passed: true,
+                            ..Default::default()
+                        },
+                        AssertionOutcome {
+                            expression: r#"{{body | message}} == "hi""#.to_string(),
+                            hydrated: r#""hello" == "hi""#.to_string(),
+                            passed: false,
+                            variables: vec![Substitution {
+                                name: "body | message".to_string(),
+                                value: Some(r#""hello""#.to_string()),
+                                kind: "string",
+                            }],
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                TestOutcome::failed(None, "connection refused".to_string()),
+            ],
+        };
+
+        let points: Vec<String> = report
+            .tests
+            .iter()
+            .enumerate()
+            .map(|(i, t)| test_point(i + 1, i, t))
+            .collect();
+
+        assert_eq!(
+            points.join("\n"),
+            r#"ok 1 - status only
+not ok 2 - post message
+  ---
+  response: "200 OK POST http://localhost:5000/message HTTP/1.1 in 0ms"
+  failures:
+    - expression: "{{body | message}} == \"hi\""
+      hydrated: "\"hello\" == \"hi\""
+      variables:
+        - name: "body | message"
+          value: "\"hello\""
+          kind: string
+  duration_ms: 0
+  ...
+not ok 3 - test #3
+  ---
+  error: "connection refused"
+  duration_ms: 0
+  ..."#
+        );
+        assert_eq!(plan(report.tests.len()), "1..3");
+    }
+}
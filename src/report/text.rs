@@ -0,0 +1,141 @@
+use super::{TestOutcome, TestReport};
+use crate::assertion::pretty_bool;
+
+pub fn render(report: &TestReport) -> String {
+    let mut buffer = String::new();
+
+    for test in &report.tests {
+        self::test(&mut buffer, test);
+        buffer.push_str("\n\n");
+    }
+
+    buffer.trim_end_matches('\n').to_string()
+}
+
+fn test(buffer: &mut String, test: &TestOutcome) {
+    if let Some(e) = &test.error {
+        buffer.push_str(pretty_bool(false));
+        buffer.push_str(e);
+        return;
+    }
+
+    buffer.push('\n');
+    buffer.push_str(pretty_bool(test.passed()));
+
+    if let Some(r) = &test.response {
+        buffer.push_str(&r.to_string());
+    }
+
+    if let Some(d) = &test.description {
+        buffer.push_str("\n📌 ");
+        buffer.push_str(d);
+    }
+
+    for (i, a) in test.attempts.iter().enumerate() {
+        buffer.push_str(&format!("\n🔁 attempt {}: {}", i + 1, a));
+    }
+
+    buffer.push('\n');
+
+    for a in &test.assertions {
+        buffer.push_str("\n  ");
+        buffer.push_str(pretty_bool(a.passed));
+        buffer.push_str(&a.hydrated);
+
+        if !a.passed {
+            for d in a.details() {
+                buffer.push_str("\n      ");
+                buffer.push_str(&d);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::{AssertionOutcome, Attempt, ResponseSummary, Substitution};
+
+    fn assertion(hydrated: &str, passed: bool) -> AssertionOutcome {
+        AssertionOutcome {
+            expression: hydrated.to_string(),
+            hydrated: hydrated.to_string(),
+            passed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_passing_and_failing_tests() {
+        let mut report = TestReport::default();
+        report.push(TestOutcome {
+            description: Some("status only".to_string()),
+            response: Some(ResponseSummary::ok("GET", "http://localhost:5000/200")),
+            assertions: vec![assertion("200 == 200", true)],
+            ..Default::default()
+        });
+        report.push(TestOutcome {
+            response: Some(ResponseSummary::ok("GET", "http://localhost:5000/message")),
+            attempts: vec![
+                Attempt {
+                    error: Some("404 Not Found".to_string()),
+                    ..Default::default()
+                },
+                Attempt {
+                    response: Some(ResponseSummary::ok("GET", "http://localhost:5000/message")),
+                    assertions: 3,
+                    failures: 3,
+                    ..Default::default()
+                },
+            ],
+            assertions: vec![
+                assertion("200 == 200", true),
+                AssertionOutcome {
+                    expression: "{{ body | id }} == 2".to_string(),
+                    hydrated: "1 == 2".to_string(),
+                    passed: false,
+                    variables: vec![Substitution {
+                        name: "body | id".to_string(),
+                        value: Some("1".to_string()),
+                        kind: "number",
+                    }],
+                    error: None,
+                },
+                AssertionOutcome {
+                    expression: "{{ body | name }} > 2".to_string(),
+                    hydrated: r#""hat" > 2"#.to_string(),
+                    passed: false,
+                    error: Some("expected a number".to_string()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        });
+        report.push(TestOutcome::failed(None, "cause".to_string()));
+
+        assert!(!report.passed());
+        assert_eq!(
+            render(&report),
+            r#"
+✅ 200 OK GET http://localhost:5000/200 HTTP/1.1 in 0ms
+📌 status only
+
+  ✅ 200 == 200
+
+
+❌ 200 OK GET http://localhost:5000/message HTTP/1.1 in 0ms
+🔁 attempt 1: 404 Not Found
+🔁 attempt 2: 200 OK GET http://localhost:5000/message HTTP/1.1 in 0ms, 3 of 3 assertion(s) failed
+
+  ✅ 200 == 200
+  ❌ 1 == 2
+      {{ body | id }} == 2
+      body | id = 1 (number)
+  ❌ "hat" > 2
+      {{ body | name }} > 2
+      error: expected a number
+
+❌ cause"#
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use crate::report::TestOutcome;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// ```toml
+/// retry = { attempts = 5, delay = "500ms", backoff = "exponential", until = "assertions" }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetryConfig {
+    /// total number of times the request is sent, including the first
+    #[serde(default = "default_attempts")]
+    pub attempts: usize,
+    /// how long to wait before the second attempt
+    #[serde(default, with = "humantime_serde")]
+    pub delay: Duration,
+    #[serde(default)]
+    pub backoff: Backoff,
+    #[serde(default)]
+    pub until: Until,
+}
+
+fn default_attempts() -> usize {
+    1
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: default_attempts(),
+            delay: Duration::ZERO,
+            backoff: Backoff::default(),
+            until: Until::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backoff {
+    /// wait `delay` between every attempt
+    #[default]
+    Fixed,
+    /// wait `delay * n` after the nth attempt
+    Linear,
+    /// wait `delay * 2^(n - 1)` after the nth attempt
+    Exponential,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Until {
+    /// every assertion passed
+    #[default]
+    Assertions,
+    /// a response was received, regardless of the assertions
+    Response,
+}
+
+impl RetryConfig {
+    /// how long to wait after the `attempt`th attempt failed, starting at 1
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let attempt = attempt.max(1) as u32;
+        match self.backoff {
+            Backoff::Fixed => self.delay,
+            Backoff::Linear => self.delay.saturating_mul(attempt),
+            Backoff::Exponential => self.delay.saturating_mul(2u32.saturating_pow(attempt - 1)),
+        }
+    }
+
+    /// true when the test does not need to be attempted again
+    pub fn done(&self, outcome: &TestOutcome) -> bool {
+        match self.until {
+            Until::Assertions => outcome.passed(),
+            Until::Response => outcome.error.is_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn delays_grow_with_backoff() {
+        let retry: RetryConfig =
+            toml::from_str("attempts = 5\ndelay = \"500ms\"\nbackoff = \"exponential\"").unwrap();
+        assert_eq!(retry.attempts, 5);
+        assert_eq!(retry.until, Until::Assertions);
+
+        let delays: Vec<u128> = (1..5).map(|a| retry.delay(a).as_millis()).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 4000]);
+
+        let linear = RetryConfig {
+            backoff: Backoff::Linear,
+            ..retry.clone()
+        };
+        assert_eq!(linear.delay(3).as_millis(), 1500);
+
+        let fixed = RetryConfig {
+            backoff: Backoff::Fixed,
+            ..retry
+        };
+        assert_eq!(fixed.delay(3).as_millis(), 500);
+    }
+
+    #[test]
+    fn until_decides_when_to_stop() {
+        let failed_assertion = TestOutcome {
+            assertions: vec![Default::default()],
+            ..Default::default()
+        };
+        let failed_request = TestOutcome::failed(None, "404 Not Found".to_string());
+
+        let assertions = RetryConfig::default();
+        assert!(!assertions.done(&failed_assertion));
+        assert!(!assertions.done(&failed_request));
+
+        let response = RetryConfig {
+            until: Until::Response,
+            ..Default::default()
+        };
+        assert!(response.done(&failed_assertion));
+        assert!(!response.done(&failed_request));
+    }
+}
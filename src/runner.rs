@@ -1,42 +1,109 @@
 use crate::{
     assertion::TestAssertions,
+    client::{Client, ClientConfig},
+    cookies::CookieJar,
     error::HatError,
     http_file::RequestBuilder,
     query::Variable,
+    report::{Attempt, TestOutcome, TestReport},
+    retry::RetryConfig,
     store::{Store, StoreUnion},
 };
+use std::{
+    collections::HashMap,
+    sync::{mpsc, OnceLock},
+    time::{Duration, Instant},
+};
 
 pub type HatTestOutput = (TestAssertions, Option<StoreUnion>);
 
 pub trait HatTestBuilder {
+    fn description(&self) -> Option<&str>;
+
+    /// name other tests refer to in `depends_on`
+    fn id(&self) -> Option<&str>;
+
+    /// ids of earlier tests that must finish before this test starts
+    fn depends_on(&self) -> &[String];
+
+    /// names of the variables this test reads, e.g. `userId` for `{{ userId }}`
+    fn variables(&self) -> Vec<String>;
+
+    /// names of the variables this test adds to the global store
+    fn outputs(&self) -> Vec<&str>;
+
+    fn retry(&self) -> Option<&RetryConfig>;
+
+    /// overrides for the `[client]` settings
+    fn client(&self) -> Option<&ClientConfig>;
+
     fn build<T: Store + RequestExecutor>(
-        self,
+        &self,
         global: &T,
-        buffer: &mut String,
+        options: &RunOptions,
     ) -> anyhow::Result<HatTestOutput>;
 }
 
+/// settings from the cli that apply to every test in a run
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    pub update_snapshots: bool,
+    /// how many tests may run at the same time, tests that depend on each other never overlap
+    pub jobs: usize,
+}
+
 pub trait RequestExecutor {
-    fn execute(&self, request: RequestBuilder) -> Result<ureq::Response, HatError>;
+    fn execute(&self, request: RequestBuilder) -> Result<TimedResponse, HatError>;
+
+    /// cookies shared by every test in the run, `None` when the cookie jar is disabled
+    fn cookies(&self) -> Option<&CookieJar>;
+}
+
+/// the response body has not been read yet, so only the time to first byte is known
+pub struct TimedResponse {
+    pub response: ureq::Response,
+    pub started: Instant,
+    pub ttfb: Duration,
 }
 
 pub struct HatRunner {
     global: Vec<StoreUnion>,
-    client: ureq::Agent,
+    client: Client,
+    options: RunOptions,
 }
 
 impl RequestExecutor for HatRunner {
-    fn execute(&self, request: RequestBuilder) -> Result<ureq::Response, HatError> {
-        let (builder, endpoint, body) = request.split();
-        let ureq_request = RequestBuilder::build(builder, endpoint, &self.client)
-            .ok_or(HatError::RequestBuilder)?;
+    fn execute(&self, request: RequestBuilder) -> Result<TimedResponse, HatError> {
+        self.execute_with(request, None)
+    }
 
-        if let Some(body) = body {
-            ureq_request.send_string(&body)
-        } else {
-            ureq_request.call()
-        }
-        .map_err(|e| HatError::HttpResponse(e.to_string()))
+    fn cookies(&self) -> Option<&CookieJar> {
+        self.client
+            .cookies_enabled(None)
+            .then(|| self.client.cookies())
+    }
+}
+
+impl HatRunner {
+    /// `client` overrides the `[client]` settings for a single test
+    fn execute_with(
+        &self,
+        request: RequestBuilder,
+        client: Option<&ClientConfig>,
+    ) -> Result<TimedResponse, HatError> {
+        let request = self.client.request(request, client)?;
+
+        let started = Instant::now();
+        let response = self.client.send(request)?;
+        let ttfb = started.elapsed();
+
+        self.client.receive(&response, client);
+
+        Ok(TimedResponse {
+            response,
+            started,
+            ttfb,
+        })
     }
 }
 
@@ -47,38 +114,467 @@ impl Store for HatRunner {
 }
 
 impl HatRunner {
-    pub fn new(global: StoreUnion, client: ureq::Agent) -> Self {
+    pub fn new(global: StoreUnion, client: Client, options: RunOptions) -> Self {
         Self {
             global: vec![global, StoreUnion::Env],
             client,
+            options,
         }
     }
 
-    pub fn test<R: HatTestBuilder, I: Iterator<Item = R>>(&mut self, tests: &mut I) -> bool {
-        let mut result = String::new();
-        let mut all_tests_pass = true;
+    /// runs every test once the tests it depends on have finished, up to `options.jobs` at a time
+    ///
+    /// `on_outcome` is called as soon as each test finishes, the report keeps the order of `tests`
+    pub fn test<R, I, F>(&self, tests: &mut I, mut on_outcome: F) -> TestReport
+    where
+        R: HatTestBuilder + Send,
+        I: Iterator<Item = R>,
+        F: FnMut(usize, &TestOutcome),
+    {
+        let mut pending: Vec<Option<R>> = tests.map(Some).collect();
+        let dependencies =
+            self::dependencies(&pending, |t: &R| self.client.cookies_enabled(t.client()));
+        let outputs: Vec<OnceLock<Option<StoreUnion>>> =
+            pending.iter().map(|_| OnceLock::new()).collect();
+        let mut outcomes: Vec<Option<TestOutcome>> = pending.iter().map(|_| None).collect();
+        let jobs = self.options.jobs.max(1);
+
+        std::thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            let mut running = 0;
 
-        for r in tests {
-            match r.build(self, &mut result) {
-                Ok((test, outputs)) => {
-                    all_tests_pass &= test.assert(&mut result);
+            loop {
+                for i in 0..pending.len() {
+                    if running >= jobs {
+                        break;
+                    }
 
-                    if let Some(o) = outputs {
-                        self.global.push(o);
+                    let ready = match &dependencies[i] {
+                        Ok(d) => d.iter().all(|d| outcomes[*d].is_some()),
+                        Err(_) => true,
+                    };
+                    if !ready {
+                        continue;
                     }
+
+                    let Some(test) = pending[i].take() else {
+                        continue;
+                    };
+
+                    let dependencies = dependencies[i].clone();
+                    // outputs of every earlier test that already finished, in config order
+                    let scope_outputs: Vec<&StoreUnion> = outputs[..i]
+                        .iter()
+                        .filter_map(|o| o.get().and_then(Option::as_ref))
+                        .collect();
+                    let sender = sender.clone();
+
+                    running += 1;
+                    scope.spawn(move || {
+                        let result = match dependencies {
+                            Ok(_) => self.run(test, scope_outputs),
+                            Err(e) => (
+                                TestOutcome::failed(test.description().map(String::from), e),
+                                None,
+                            ),
+                        };
+                        let _ = sender.send((i, result));
+                    });
                 }
-                Err(e) => {
-                    all_tests_pass = false;
-                    result.push_str(crate::assertion::pretty_bool(false));
-                    result.push_str(e.to_string().as_str());
+
+                if running == 0 {
+                    break;
+                }
+
+                let Ok((i, (outcome, output))) = receiver.recv() else {
+                    break;
+                };
+                running -= 1;
+
+                let _ = outputs[i].set(output);
+                on_outcome(i, &outcome);
+                outcomes[i] = Some(outcome);
+            }
+        });
+
+        let mut report = TestReport::default();
+        for outcome in outcomes.into_iter().flatten() {
+            report.push(outcome);
+        }
+
+        report
+    }
+
+    /// builds the test until `retry.until` is satisfied or every attempt was used
+    fn run<R: HatTestBuilder>(
+        &self,
+        test: R,
+        outputs: Vec<&StoreUnion>,
+    ) -> (TestOutcome, Option<StoreUnion>) {
+        let description = test.description().map(|d| d.to_string());
+        let retry = test.retry().cloned().unwrap_or_default();
+        let start = Instant::now();
+        let scope = TestScope {
+            runner: self,
+            client: test.client(),
+            outputs,
+        };
+
+        let mut attempts = Vec::new();
+        let (mut outcome, output) = loop {
+            let attempt_start = Instant::now();
+            let (mut outcome, output) = match test.build(&scope, &self.options) {
+                Ok((test, output)) => {
+                    let mut outcome = test.assert();
+                    if let Some(o) = &output {
+                        outcome.outputs = o.entries();
+                    }
+                    (outcome, output)
                 }
+                Err(e) => (
+                    TestOutcome::failed(description.clone(), e.to_string()),
+                    None,
+                ),
             };
+            outcome.duration = attempt_start.elapsed();
+
+            if attempts.len() + 1 >= retry.attempts || retry.done(&outcome) {
+                break (outcome, output);
+            }
+
+            log::info!(
+                "attempt {} of {} failed",
+                attempts.len() + 1,
+                retry.attempts
+            );
+            attempts.push(Attempt::from(outcome));
+            std::thread::sleep(retry.delay(attempts.len()));
+        };
+
+        outcome.attempts = attempts;
+        outcome.duration = start.elapsed();
+        (outcome, output)
+    }
+}
+
+/// what a single test can see: the global store and the outputs of earlier tests
+struct TestScope<'a> {
+    runner: &'a HatRunner,
+    client: Option<&'a ClientConfig>,
+    outputs: Vec<&'a StoreUnion>,
+}
+
+impl Store for TestScope<'_> {
+    fn fetch_value<'a>(&'a self, key: &'a str) -> Option<Variable<'a>> {
+        self.runner
+            .fetch_value(key)
+            .or_else(|| self.outputs.iter().find_map(|s| s.fetch_value(key)))
+    }
+}
+
+impl RequestExecutor for TestScope<'_> {
+    fn execute(&self, request: RequestBuilder) -> Result<TimedResponse, HatError> {
+        self.runner.execute_with(request, self.client)
+    }
+
+    fn cookies(&self) -> Option<&CookieJar> {
+        let client = &self.runner.client;
+        client
+            .cookies_enabled(self.client)
+            .then(|| client.cookies())
+    }
+}
 
-            result.push_str("\n\n");
+/// indexes of the earlier tests each test has to wait for
+///
+/// a test depends on the tests listed in its `depends_on` and on every earlier test whose
+/// outputs define a variable it reads, so the earliest of them is the value it sees, the same
+/// as when the tests run one at a time
+///
+/// tests that `share_cookies` depend on every earlier test that does, since a cookie set by
+/// one of them is sent by the next
+fn dependencies<R: HatTestBuilder>(
+    tests: &[Option<R>],
+    share_cookies: impl Fn(&R) -> bool,
+) -> Vec<Result<Vec<usize>, String>> {
+    let mut ids = HashMap::<&str, usize>::new();
+    let mut defined_by = HashMap::<&str, Vec<usize>>::new();
+    let mut cookies = Vec::new();
+    let mut dependencies = Vec::with_capacity(tests.len());
+
+    for (i, test) in tests.iter().enumerate() {
+        let Some(test) = test else {
+            dependencies.push(Ok(Vec::new()));
+            continue;
+        };
+
+        let mut depends_on = Vec::new();
+        let mut error = None;
+
+        for id in test.depends_on() {
+            match ids.get(id.as_str()) {
+                Some(d) => depends_on.push(*d),
+                None => {
+                    error = Some(format!(
+                        "depends on '{}', but no earlier test has that id",
+                        id
+                    ))
+                }
+            }
+        }
+
+        for variable in test.variables() {
+            if let Some(d) = defined_by.get(variable.as_str()) {
+                depends_on.extend(d);
+            }
+        }
+
+        let share_cookies = share_cookies(test);
+        if share_cookies {
+            depends_on.extend(&cookies);
+        }
+
+        depends_on.sort();
+        depends_on.dedup();
+        dependencies.push(error.map(Err).unwrap_or(Ok(depends_on)));
+
+        if let Some(id) = test.id() {
+            ids.insert(id, i);
+        }
+        for output in test.outputs() {
+            defined_by.entry(output).or_default().push(i);
+        }
+        if share_cookies {
+            cookies.push(i);
+        }
+    }
+
+    dependencies
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{query::Content, test::server};
+
+    #[derive(Default)]
+    struct Fake {
+        id: Option<&'static str>,
+        depends_on: Vec<String>,
+        variables: Vec<&'static str>,
+        outputs: Vec<&'static str>,
+        /// the value of every output, a test without outputs or an assertion is not executed
+        value: &'static str,
+        assertion: Option<&'static str>,
+        delay: Duration,
+    }
+
+    impl HatTestBuilder for Fake {
+        fn description(&self) -> Option<&str> {
+            None
+        }
+
+        fn id(&self) -> Option<&str> {
+            self.id
+        }
+
+        fn depends_on(&self) -> &[String] {
+            &self.depends_on
+        }
+
+        fn variables(&self) -> Vec<String> {
+            self.variables.iter().map(|v| v.to_string()).collect()
         }
 
-        println!("{}", result.trim_end_matches('\n'));
+        fn outputs(&self) -> Vec<&str> {
+            self.outputs.clone()
+        }
+
+        fn retry(&self) -> Option<&RetryConfig> {
+            None
+        }
+
+        fn client(&self) -> Option<&ClientConfig> {
+            None
+        }
+
+        fn build<T: Store + RequestExecutor>(
+            &self,
+            global: &T,
+            _: &RunOptions,
+        ) -> anyhow::Result<HatTestOutput> {
+            if self.outputs.is_empty() && self.assertion.is_none() {
+                anyhow::bail!("not executed")
+            }
+            std::thread::sleep(self.delay);
+
+            let assertions = self
+                .assertion
+                .iter()
+                .map(|a| {
+                    let hydrated = global.match_and_replace(a, |v| v.as_literal());
+                    (a.to_string(), hydrated, Vec::new())
+                })
+                .collect();
+            let output = self
+                .outputs
+                .iter()
+                .map(|o| (o.to_string(), Content::new(self.value.to_string())))
+                .collect();
+
+            Ok((
+                crate::assertion::new(
+                    "GET http://localhost".to_string(),
+                    crate::report::ResponseSummary::ok("GET", "http://localhost"),
+                    None,
+                    assertions,
+                ),
+                Some(StoreUnion::MapStringToContent(output)),
+            ))
+        }
+    }
+
+    #[test]
+    fn dependencies_come_from_outputs_and_depends_on() {
+        let tests = vec![
+            Some(Fake {
+                id: Some("create"),
+                outputs: vec!["userId"],
+                ..Default::default()
+            }),
+            Some(Fake {
+                variables: vec!["base"],
+                ..Default::default()
+            }),
+            Some(Fake {
+                variables: vec!["base", "userId"],
+                outputs: vec!["userId"],
+                ..Default::default()
+            }),
+            Some(Fake {
+                variables: vec!["userId"],
+                depends_on: vec!["create".to_string()],
+                ..Default::default()
+            }),
+            Some(Fake {
+                depends_on: vec!["later".to_string()],
+                ..Default::default()
+            }),
+        ];
+
+        assert_eq!(
+            dependencies(&tests, |_| false),
+            vec![
+                Ok(vec![]),
+                Ok(vec![]),
+                Ok(vec![0]),
+                Ok(vec![0, 2]),
+                Err("depends on 'later', but no earlier test has that id".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn redefined_output_is_read_from_the_earliest_test() {
+        let runner = HatRunner::new(
+            StoreUnion::MapStringToContent(Default::default()),
+            Client::new(Default::default()),
+            RunOptions {
+                jobs: 2,
+                ..Default::default()
+            },
+        );
+
+        // the second definition finishes first, the reader still waits for both
+        let tests = vec![
+            Some(Fake {
+                outputs: vec!["token"],
+                value: "first",
+                delay: Duration::from_millis(200),
+                ..Default::default()
+            }),
+            Some(Fake {
+                outputs: vec!["token"],
+                value: "second",
+                ..Default::default()
+            }),
+            Some(Fake {
+                variables: vec!["token"],
+                assertion: Some(r#"{{ token }} == "first""#),
+                ..Default::default()
+            }),
+        ];
+        assert_eq!(dependencies(&tests, |_| false)[2], Ok(vec![0, 1]));
+
+        let report = runner.test(&mut tests.into_iter().flatten(), |_, _| {});
+        assert!(report.passed(), "{:#?}", report.tests[2].assertions);
+    }
+
+    #[test]
+    fn tests_sharing_the_cookie_jar_run_in_order() -> anyhow::Result<()> {
+        let (port, server) = server::serve(vec![
+            server::response("200 OK", &["Set-Cookie: session=abc; Path=/"], ""),
+            server::response("200 OK", &[], ""),
+        ]);
+
+        // `me` reads no output of `login`, only the session cookie it sets
+        let config: crate::config::Config = toml::from_str(&format!(
+            r#"
+environment = {{ base = "http://127.0.0.1:{}" }}
+client = {{ cookies = true }}
+
+[[tests]]
+http = "POST {{{{ base }}}}/login"
+assertions = "{{{{ status }}}} == 200"
+
+[[tests]]
+http = "GET {{{{ base }}}}/me"
+assertions = "{{{{ status }}}} == 200"
+"#,
+            port
+        ))?;
+        let environment = config
+            .environment
+            .into_iter()
+            .map(|(k, v)| (k, Content::new(v)))
+            .collect();
+        let runner = HatRunner::new(
+            StoreUnion::MapStringToContent(environment),
+            Client::new(config.client),
+            RunOptions {
+                jobs: 4,
+                ..Default::default()
+            },
+        );
+
+        let report = runner.test(&mut config.tests.into_iter(), |_, _| {});
+        assert!(report.passed());
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0].head[0], "POST /login HTTP/1.1");
+        assert_eq!(requests[1].head[0], "GET /me HTTP/1.1");
+        assert_eq!(requests[1].header("cookie"), Some("session=abc"));
+        Ok(())
+    }
+
+    #[test]
+    fn report_keeps_config_order() {
+        let runner = HatRunner::new(
+            StoreUnion::MapStringToContent(Default::default()),
+            Client::new(Default::default()),
+            RunOptions {
+                jobs: 4,
+                ..Default::default()
+            },
+        );
+
+        let mut finished = Vec::new();
+        let tests = (0..6).map(|_| Fake::default());
+        let report = runner.test(&mut tests.into_iter(), |i, _| finished.push(i));
 
-        all_tests_pass
+        finished.sort();
+        assert_eq!(finished, (0..6).collect::<Vec<usize>>());
+        assert_eq!(report.tests.len(), 6);
+        assert!(report.tests.iter().all(|t| t.error.is_some()));
     }
 }
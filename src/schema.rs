@@ -0,0 +1,120 @@
+use crate::{error::HatError, query::Variable, report::AssertionOutcome, store::Store};
+use serde_json::Value;
+use std::path::Path;
+
+/// validates the response body against a JSON Schema (draft 2020-12) file
+/// every violation is reported as its own failed assertion
+pub fn validate<P: AsRef<Path>, S: Store>(
+    path: P,
+    store: &S,
+) -> Result<Vec<AssertionOutcome>, HatError> {
+    let name = path.as_ref().to_string_lossy().to_string();
+    let schema = std::fs::read_to_string(path.as_ref())
+        .map_err(|e| HatError::Schema(format!("could not read {}, cause: {}", name, e)))?;
+    let schema: Value = serde_json::from_str(&schema)
+        .map_err(|e| HatError::Schema(format!("{} is not valid json, cause: {}", name, e)))?;
+
+    let body = match store.fetch_value("body") {
+        Some(Variable::Json(value)) => serde_json::from_str(value.json()).ok(),
+        _ => None,
+    };
+
+    let expression = format!("body conforms \"{}\"", name);
+
+    let Some(body) = body else {
+        return Ok(vec![AssertionOutcome {
+            hydrated: expression.to_string(),
+            expression,
+            passed: false,
+            error: Some("body is not json".to_string()),
+            ..Default::default()
+        }]);
+    };
+
+    self::validate_value(&schema, &body, expression)
+        .map_err(|e| HatError::Schema(format!("{} is not a valid schema, cause: {}", name, e)))
+}
+
+pub fn validate_value(
+    schema: &Value,
+    body: &Value,
+    expression: String,
+) -> Result<Vec<AssertionOutcome>, String> {
+    let validator = jsonschema::draft202012::new(schema).map_err(|e| e.to_string())?;
+
+    let violations: Vec<AssertionOutcome> = validator
+        .iter_errors(body)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            AssertionOutcome {
+                expression: expression.to_string(),
+                hydrated: format!("{}: {}", if path.is_empty() { "/" } else { &path }, e),
+                passed: false,
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    if violations.is_empty() {
+        return Ok(vec![AssertionOutcome {
+            hydrated: expression.to_string(),
+            expression,
+            passed: true,
+            ..Default::default()
+        }]);
+    }
+
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "required": ["id", "message"],
+            "properties": {
+                "id": { "type": "integer" },
+                "message": { "type": "string" },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            }
+        })
+    }
+
+    #[test]
+    fn conforming_body_passes() -> Result<(), String> {
+        let body = json!({ "id": 1, "message": "hello", "tags": ["a"] });
+        let outcomes = validate_value(&schema(), &body, "body conforms".to_string())?;
+
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].passed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn every_violation_is_reported() -> Result<(), String> {
+        let body = json!({ "id": "1", "tags": ["a", 2] });
+        let outcomes = validate_value(&schema(), &body, "body conforms".to_string())?;
+        let mut paths: Vec<&str> = outcomes
+            .iter()
+            .map(|o| o.hydrated.split(':').next().unwrap_or_default())
+            .collect();
+        paths.sort();
+
+        assert!(outcomes.iter().all(|o| !o.passed));
+        assert_eq!(paths, vec!["/", "/id", "/tags/1"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_schema_is_an_error() {
+        let schema = json!({ "type": 12 });
+        assert!(validate_value(&schema, &json!({}), "body conforms".to_string()).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{auth::hex, error::HatError, store::Store, utc::Utc};
+use hmac::{digest::core_api::BlockSizeUser, Mac, SimpleHmac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::{collections::BTreeMap, time::SystemTime};
+
+/// signs the request once every header is set, from `[sign]` or a test's `[tests.sign]`
+///
+/// ```toml
+/// [sign]
+/// type = "sigv4"
+/// access_key = "{{ AWS_ACCESS_KEY_ID }}"
+/// secret_key = "{{ AWS_SECRET_ACCESS_KEY }}"
+/// region = "us-east-1"
+/// service = "execute-api"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SigningConfig {
+    /// AWS Signature Version 4
+    Sigv4(SigV4Config),
+    /// an HMAC of the method, path, headers, and body in a header
+    Hmac(HmacConfig),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SigV4Config {
+    pub access_key: String,
+    pub secret_key: String,
+    /// sent as `X-Amz-Security-Token` for temporary credentials
+    pub session_token: Option<String>,
+    pub region: String,
+    pub service: String,
+}
+
+/// the signed string is the method, the path with its query, a `name:value` line for each of
+/// `headers`, and the body, separated by newlines
+///
+/// ```toml
+/// [sign]
+/// type = "hmac"
+/// secret = "{{ webhookSecret }}"
+/// algorithm = "sha256"
+/// header = "X-Signature"
+/// headers = ["x-timestamp"]
+/// encoding = "hex"
+/// prefix = "sha256="
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HmacConfig {
+    pub secret: String,
+    #[serde(default)]
+    pub algorithm: HmacAlgorithm,
+    /// header the signature is sent in
+    #[serde(default = "default_header")]
+    pub header: String,
+    /// headers included in the signed string, in this order
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// written before the signature, e.g. `sha256=`
+    #[serde(default)]
+    pub prefix: String,
+}
+
+fn default_header() -> String {
+    "X-Signature".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HmacAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+/// headers that proxies and the http client may change after signing
+const UNSIGNED_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "user-agent",
+    "expect",
+    "x-amzn-trace-id",
+];
+
+impl SigningConfig {
+    /// replaces the `{{ variables }}` in every setting
+    pub fn hydrate<S: Store>(&self, store: &S) -> SigningConfig {
+        let hydrate = |s: &String| store.match_and_replace(s, |v| v.as_value());
+
+        match self {
+            SigningConfig::Sigv4(c) => SigningConfig::Sigv4(SigV4Config {
+                access_key: hydrate(&c.access_key),
+                secret_key: hydrate(&c.secret_key),
+                session_token: c.session_token.as_ref().map(hydrate),
+                region: hydrate(&c.region),
+                service: hydrate(&c.service),
+            }),
+            SigningConfig::Hmac(c) => SigningConfig::Hmac(HmacConfig {
+                secret: hydrate(&c.secret),
+                ..c.clone()
+            }),
+        }
+    }
+
+    /// every setting that can hold a variable, used to find the variables a test reads
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            SigningConfig::Sigv4(c) => [&c.access_key, &c.secret_key, &c.region, &c.service]
+                .into_iter()
+                .chain(&c.session_token)
+                .map(String::as_str)
+                .collect(),
+            SigningConfig::Hmac(c) => vec![&c.secret],
+        }
+    }
+
+    pub fn sign(&self, request: ureq::Request, body: &[u8]) -> Result<ureq::Request, HatError> {
+        match self {
+            SigningConfig::Sigv4(c) => c.sign(request, body, SystemTime::now()),
+            SigningConfig::Hmac(c) => c.sign(request, body),
+        }
+    }
+}
+
+impl SigV4Config {
+    fn sign(
+        &self,
+        request: ureq::Request,
+        body: &[u8],
+        now: SystemTime,
+    ) -> Result<ureq::Request, HatError> {
+        let url = url::Url::parse(request.url()).map_err(|e| HatError::Signing(e.to_string()))?;
+        let (date, timestamp) = self::timestamp(now);
+        let payload = hex(&Sha256::digest(body));
+
+        let mut request = request.set("X-Amz-Date", &timestamp);
+        if let Some(token) = &self.session_token {
+            request = request.set("X-Amz-Security-Token", token);
+        }
+        if self.service == "s3" {
+            request = request.set("X-Amz-Content-Sha256", &payload);
+        }
+
+        let mut headers = BTreeMap::<String, Vec<String>>::new();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        headers.insert("host".to_string(), vec![host]);
+        for name in request.header_names() {
+            if UNSIGNED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            let values = request
+                .all(&name)
+                .into_iter()
+                .map(|v| v.split_whitespace().collect::<Vec<&str>>().join(" "))
+                .collect();
+            headers.insert(name, values);
+        }
+
+        // every service except s3 expects the path to be encoded twice
+        let path = if self.service == "s3" {
+            url.path().to_string()
+        } else {
+            url.path()
+                .split('/')
+                .map(self::uri_encode)
+                .collect::<Vec<String>>()
+                .join("/")
+        };
+        let mut query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| (self::uri_encode(&k), self::uri_encode(&v)))
+            .collect();
+        query.sort();
+
+        let signed_headers = headers.keys().cloned().collect::<Vec<String>>().join(";");
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            request.method(),
+            path,
+            query
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<String>>()
+                .join("&"),
+            headers
+                .iter()
+                .map(|(k, v)| format!("{}:{}\n", k, v.join(",")))
+                .collect::<String>(),
+            signed_headers,
+            payload
+        );
+        log::debug!("canonical request: {}", canonical_request);
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = [
+            date.as_str(),
+            self.region.as_str(),
+            self.service.as_str(),
+            "aws4_request",
+        ]
+        .into_iter()
+        .fold(
+            format!("AWS4{}", self.secret_key).into_bytes(),
+            |key, part| self::hmac::<Sha256>(&key, part.as_bytes()),
+        );
+        let signature = hex(&self::hmac::<Sha256>(&key, string_to_sign.as_bytes()));
+
+        Ok(request.set(
+            "Authorization",
+            &format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key, scope, signed_headers, signature
+            ),
+        ))
+    }
+}
+
+impl HmacConfig {
+    fn sign(&self, request: ureq::Request, body: &[u8]) -> Result<ureq::Request, HatError> {
+        let url = url::Url::parse(request.url()).map_err(|e| HatError::Signing(e.to_string()))?;
+
+        let mut message = format!("{}\n{}", request.method(), url.path());
+        if let Some(query) = url.query() {
+            message.push_str(&format!("?{}", query));
+        }
+        for name in &self.headers {
+            let value = request.header(name).ok_or_else(|| {
+                HatError::Signing(format!(
+                    "'{}' is signed, but the request does not have it",
+                    name
+                ))
+            })?;
+            message.push_str(&format!("\n{}:{}", name.to_lowercase(), value.trim()));
+        }
+        message.push('\n');
+        let mut message = message.into_bytes();
+        message.extend_from_slice(body);
+
+        let secret = self.secret.as_bytes();
+        let signature = match self.algorithm {
+            HmacAlgorithm::Sha256 => self::hmac::<Sha256>(secret, &message),
+            HmacAlgorithm::Sha512 => self::hmac::<Sha512>(secret, &message),
+        };
+        let signature = match self.encoding {
+            Encoding::Hex => hex(&signature),
+            Encoding::Base64 => {
+                use base64::Engine;
+                base64::engine::general_purpose::STANDARD.encode(signature)
+            }
+        };
+
+        Ok(request.set(&self.header, &format!("{}{}", self.prefix, signature)))
+    }
+}
+
+fn hmac<D: Digest + BlockSizeUser>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = SimpleHmac::<D>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// percent-encodes everything except the unreserved characters of RFC 3986
+fn uri_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// `(YYYYMMDD, YYYYMMDDTHHMMSSZ)` in UTC
+fn timestamp(time: SystemTime) -> (String, String) {
+    let utc = Utc::from(time);
+    let date = format!("{:04}{:02}{:02}", utc.year, utc.month, utc.day);
+    let timestamp = format!(
+        "{}T{:02}{:02}{:02}Z",
+        date, utc.hour, utc.minute, utc.second
+    );
+    (date, timestamp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn sigv4() -> SigV4Config {
+        SigV4Config {
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+            region: "us-east-1".to_string(),
+            service: "service".to_string(),
+        }
+    }
+
+    /// 2015-08-30T12:36:00Z, the time used by the AWS test suite
+    fn now() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1440938160)
+    }
+
+    #[test]
+    fn sigv4_matches_the_aws_test_suite() {
+        // get-vanilla
+        let request = sigv4()
+            .sign(ureq::get("http://example.amazonaws.com/"), b"", now())
+            .unwrap();
+        assert_eq!(request.header("x-amz-date"), Some("20150830T123600Z"));
+        assert_eq!(
+            request.header("authorization"),
+            Some("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31")
+        );
+
+        // get-vanilla-query-order-key-case
+        let request = sigv4()
+            .sign(
+                ureq::get("http://example.amazonaws.com/?Param2=value2&Param1=value1"),
+                b"",
+                now(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.header("authorization"),
+            Some("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500")
+        );
+    }
+
+    #[test]
+    fn hmac_signs_method_path_headers_and_body() {
+        let hmac: HmacConfig =
+            toml::from_str("secret = \"key\"\nheaders = [\"X-Timestamp\"]\nprefix = \"sha256=\"")
+                .unwrap();
+        let request = ureq::post("http://localhost/hooks?id=1").set("X-Timestamp", "1700000000");
+
+        let request = hmac.sign(request, br#"{"event":"created"}"#).unwrap();
+        let expected = hex(&self::hmac::<Sha256>(
+            b"key",
+            b"POST\n/hooks?id=1\nx-timestamp:1700000000\n{\"event\":\"created\"}",
+        ));
+        assert_eq!(
+            request.header("x-signature"),
+            Some(format!("sha256={}", expected).as_str())
+        );
+
+        // the HMAC-SHA256 example from RFC 4231 test case 2
+        assert_eq!(
+            hex(&self::hmac::<Sha256>(
+                b"Jefe",
+                b"what do ya want for nothing?"
+            )),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
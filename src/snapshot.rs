@@ -0,0 +1,311 @@
+use crate::{error::HatError, report::AssertionOutcome, store::Store};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// headers that change on every response, they are never written to or compared with a snapshot
+const VOLATILE_HEADERS: [&str; 14] = [
+    "date",
+    "age",
+    "expires",
+    "last-modified",
+    "etag",
+    "set-cookie",
+    "x-request-id",
+    "x-correlation-id",
+    "x-amzn-requestid",
+    "x-amzn-trace-id",
+    "cf-ray",
+    "server-timing",
+    "traceparent",
+    "x-runtime",
+];
+
+/// ```toml
+/// snapshot = "snapshots/get_users.json"
+/// # OR
+/// [tests.snapshot]
+/// path = "snapshots/get_users.json"
+/// ignore = ["body.users.#.id", "headers.etag"]
+/// ```
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum SnapshotConfig {
+    Path(String),
+    Table {
+        path: String,
+        #[serde(default)]
+        ignore: Vec<String>,
+    },
+}
+
+impl SnapshotConfig {
+    fn path(&self) -> &str {
+        match self {
+            SnapshotConfig::Path(path) | SnapshotConfig::Table { path, .. } => path,
+        }
+    }
+
+    fn ignore(&self) -> &[String] {
+        match self {
+            SnapshotConfig::Path(_) => &[],
+            SnapshotConfig::Table { ignore, .. } => ignore,
+        }
+    }
+
+    /// writes the snapshot when it does not exist yet or `update` is set,
+    /// otherwise every difference from the stored snapshot is a failed assertion
+    pub fn assert<S: Store>(
+        &self,
+        store: &S,
+        update: bool,
+    ) -> Result<Vec<AssertionOutcome>, HatError> {
+        let path = Path::new(self.path());
+        let expression = format!("response matches snapshot \"{}\"", self.path());
+        let live = self::normalize(self::capture(store), self.ignore());
+
+        if update || !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let json = serde_json::to_string_pretty(&live)
+                .map_err(|e| HatError::Snapshot(e.to_string()))?;
+            std::fs::write(path, json + "\n")?;
+
+            return Ok(vec![AssertionOutcome {
+                hydrated: format!("snapshot written to \"{}\"", self.path()),
+                expression,
+                passed: true,
+                ..Default::default()
+            }]);
+        }
+
+        let stored = std::fs::read_to_string(path)?;
+        let stored: Value = serde_json::from_str(&stored).map_err(|e| {
+            HatError::Snapshot(format!("{} is not valid json, cause: {}", self.path(), e))
+        })?;
+        let stored = self::normalize(stored, self.ignore());
+
+        let mut differences = Vec::new();
+        self::diff("", &stored, &live, &mut differences);
+
+        if differences.is_empty() {
+            return Ok(vec![AssertionOutcome {
+                hydrated: expression.to_string(),
+                expression,
+                passed: true,
+                ..Default::default()
+            }]);
+        }
+
+        Ok(differences
+            .into_iter()
+            .map(|d| AssertionOutcome {
+                expression: expression.to_string(),
+                hydrated: d,
+                passed: false,
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+/// `{ "status": .., "headers": { .. }, "body": .. }` from the response store
+fn capture<S: Store>(store: &S) -> Value {
+    let mut snapshot = Map::new();
+
+    for key in ["status", "headers", "body"] {
+        if let Some(value) = store.fetch_value(key).and_then(|v| v.to_json()) {
+            snapshot.insert(key.to_string(), value);
+        }
+    }
+
+    Value::Object(snapshot)
+}
+
+/// removes the volatile headers and the ignored paths, snapshots written before a header was
+/// volatile still match
+fn normalize(mut snapshot: Value, ignore: &[String]) -> Value {
+    if let Some(Value::Object(headers)) = snapshot.get_mut("headers") {
+        headers.retain(|name, _| !VOLATILE_HEADERS.contains(&name.to_lowercase().as_str()));
+    }
+
+    for path in ignore {
+        let segments: Vec<&str> = path.split('.').collect();
+        self::remove(&mut snapshot, &segments);
+    }
+
+    snapshot
+}
+
+/// removes the value at a dotted path, `#` or `*` matches every array item or object field
+fn remove(value: &mut Value, segments: &[&str]) {
+    let Some((first, rest)) = segments.split_first() else {
+        return;
+    };
+
+    let wildcard = *first == "#" || *first == "*";
+
+    match value {
+        Value::Object(map) if rest.is_empty() => {
+            if wildcard {
+                map.clear();
+            } else {
+                map.remove(*first);
+            }
+        }
+        Value::Object(map) => {
+            if wildcard {
+                map.values_mut().for_each(|v| self::remove(v, rest));
+            } else if let Some(v) = map.get_mut(*first) {
+                self::remove(v, rest);
+            }
+        }
+        Value::Array(items) if rest.is_empty() => {
+            if wildcard {
+                items.clear();
+            } else if let Ok(i) = first.parse::<usize>() {
+                if i < items.len() {
+                    items.remove(i);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if wildcard {
+                items.iter_mut().for_each(|v| self::remove(v, rest));
+            } else if let Some(v) = first.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                self::remove(v, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn diff(path: &str, expected: &Value, actual: &Value, differences: &mut Vec<String>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            for (key, value) in e {
+                match a.get(key) {
+                    Some(v) => self::diff(&join(key), value, v, differences),
+                    None => differences.push(format!(
+                        "{}: expected {}, but it is missing",
+                        join(key),
+                        value
+                    )),
+                }
+            }
+
+            for (key, value) in a.iter().filter(|(key, _)| !e.contains_key(*key)) {
+                differences.push(format!("{}: unexpected {}", join(key), value));
+            }
+        }
+        (Value::Array(e), Value::Array(a)) if e.len() == a.len() => {
+            for (i, (e, a)) in e.iter().zip(a).enumerate() {
+                self::diff(&join(&i.to_string()), e, a, differences);
+            }
+        }
+        _ if expected != actual => {
+            differences.push(format!(
+                "{}: expected {}, but got {}",
+                if path.is_empty() { "." } else { path },
+                expected,
+                actual
+            ));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ignored_paths_are_removed() {
+        let snapshot = json!({
+            "headers": { "etag": "abc", "content-type": "application/json" },
+            "body": { "users": [{ "id": 1, "name": "a" }, { "id": 2, "name": "b" }], "createdAt": "today" }
+        });
+
+        let normalized = normalize(
+            snapshot,
+            &[
+                "headers.etag".to_string(),
+                "body.users.#.id".to_string(),
+                "body.createdAt".to_string(),
+            ],
+        );
+
+        assert_eq!(
+            normalized,
+            json!({
+                "headers": { "content-type": "application/json" },
+                "body": { "users": [{ "name": "a" }, { "name": "b" }] }
+            })
+        );
+    }
+
+    #[test]
+    fn volatile_headers_are_removed() {
+        let snapshot = json!({
+            "status": 200,
+            "headers": {
+                "Date": "Sun, 18 Oct 2026 08:00:00 GMT",
+                "set-cookie": "session=abc",
+                "x-request-id": "7f3a",
+                "content-type": "application/json"
+            }
+        });
+
+        assert_eq!(
+            normalize(snapshot, &[]),
+            json!({ "status": 200, "headers": { "content-type": "application/json" } })
+        );
+    }
+
+    #[test]
+    fn differences_are_reported_by_path() {
+        let expected =
+            json!({ "status": 200, "body": { "id": 1, "tags": ["a", "b"], "gone": true } });
+        let actual = json!({ "status": 201, "body": { "id": 1, "tags": ["a", "c"], "new": null } });
+
+        let mut differences = Vec::new();
+        diff("", &expected, &actual, &mut differences);
+
+        assert_eq!(
+            differences,
+            vec![
+                "body.gone: expected true, but it is missing",
+                "body.tags.1: expected \"b\", but got \"c\"",
+                "body.new: unexpected null",
+                "status: expected 200, but got 201",
+            ]
+        );
+    }
+
+    #[test]
+    fn snapshot_config_is_a_path_or_table() {
+        #[derive(Deserialize)]
+        struct Test {
+            snapshot: SnapshotConfig,
+        }
+
+        let t: Test = toml::from_str(r#"snapshot = "a.json""#).unwrap();
+        assert_eq!(t.snapshot.path(), "a.json");
+        assert!(t.snapshot.ignore().is_empty());
+
+        let t: Test =
+            toml::from_str("[snapshot]\npath = \"a.json\"\nignore = [\"body.id\"]").unwrap();
+        assert_eq!(t.snapshot.ignore(), &["body.id".to_string()]);
+    }
+}
@@ -3,6 +3,15 @@ use std::{collections::HashMap, slice::Iter};
 
 pub type ContentMap = HashMap<String, Content>;
 
+/// a `{{ <name> }}` variable replaced by `match_and_capture`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Capture {
+    pub name: String,
+    /// `None` when the variable could not be found
+    pub value: Option<String>,
+    pub kind: &'static str,
+}
+
 #[allow(dead_code)]
 pub enum StoreUnion {
     MapStringToContent(ContentMap),
@@ -10,7 +19,7 @@ pub enum StoreUnion {
 }
 
 impl Store for StoreUnion {
-    fn fetch_value<'a>(&'a self, key: &'a str) -> Option<Variable<'_>> {
+    fn fetch_value<'a>(&'a self, key: &'a str) -> Option<Variable<'a>> {
         let value = match self {
             // key = headers | content-type
             StoreUnion::MapStringToContent(s) => {
@@ -32,6 +41,23 @@ impl Store for StoreUnion {
     }
 }
 
+impl StoreUnion {
+    /// key/value pairs held by the store, sorted by key
+    pub fn entries(&self) -> Vec<(String, String)> {
+        match self {
+            StoreUnion::MapStringToContent(s) => {
+                let mut entries: Vec<(String, String)> = s
+                    .iter()
+                    .map(|(key, content)| (key.to_string(), content.as_str().to_string()))
+                    .collect();
+                entries.sort();
+                entries
+            }
+            StoreUnion::Env => Vec::new(),
+        }
+    }
+}
+
 pub struct StoreComposed<'a, 'b, A: Store, B: Store> {
     store_1: &'a A,
     store_2: &'b B,
@@ -58,21 +84,52 @@ lazy_static::lazy_static! {
     static ref REGEX: Regex = Regex::new(PATTERN).expect("pattern is invalid");
 }
 
+/// names of the `{{ <name> | <query> }}` variables used in `text`
+pub fn variables(text: &str) -> impl Iterator<Item = &str> {
+    REGEX.captures_iter(text).filter_map(|cap| {
+        let name = cap.get(1)?.as_str().split('|').next()?.trim();
+        Some(name)
+    })
+}
+
 pub trait Store {
     fn fetch_value<'a>(&'a self, key: &'a str) -> Option<Variable<'a>>;
 
     fn match_and_replace<F: Fn(Variable) -> String>(&self, hydrate: &str, render: F) -> String {
+        self.match_and_capture(hydrate, render).0
+    }
+
+    /// same as `match_and_replace`, but also returns each variable that was replaced
+    fn match_and_capture<F: Fn(Variable) -> String>(
+        &self,
+        hydrate: &str,
+        render: F,
+    ) -> (String, Vec<Capture>) {
+        let mut substitutions = Vec::new();
+
         let result = REGEX.replace_all(hydrate, |cap: &Captures| {
             let key = &cap[1];
             if let Some(x) = self.fetch_value(key) {
-                return render(x);
+                let kind = x.kind();
+                let value = render(x);
+                substitutions.push(Capture {
+                    name: key.trim().to_string(),
+                    value: Some(value.clone()),
+                    kind,
+                });
+                return value;
             }
 
             log::debug!("could not find {}, captures: {:#?}", key, &cap);
+            substitutions.push(Capture {
+                name: key.trim().to_string(),
+                value: None,
+                kind: "missing",
+            });
             format!("{{{{{}}}}}", key)
         });
 
-        result.into_owned()
+        (result.into_owned(), substitutions)
     }
 
     fn compose<'a, 'b, B: Store>(&'a self, store: &'b B) -> StoreComposed<'a, 'b, Self, B>
@@ -181,6 +238,40 @@ mod test {
         assert_eq!(hydrated, "\"hello world\" == \"hello world\"");
     }
 
+    #[test]
+    fn captures_replaced_variables() {
+        let mut map = ContentMap::new();
+        map.insert("body".to_string(), self::parse(r#"{ "id": 1 }"#));
+
+        let store = StoreUnion::MapStringToContent(map);
+
+        let (hydrated, substitutions) =
+            store.match_and_capture("{{ body | id }} == {{ missing }}", |v| v.as_literal());
+        assert_eq!(hydrated, "1 == {{ missing }}");
+        assert_eq!(
+            substitutions,
+            vec![
+                Capture {
+                    name: "body | id".to_string(),
+                    value: Some("1".to_string()),
+                    kind: "number",
+                },
+                Capture {
+                    name: "missing".to_string(),
+                    value: None,
+                    kind: "missing",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn variable_names_ignore_queries() {
+        let names: Vec<&str> =
+            variables("GET {{base}}/users/{{ userId }}\n{{ body | id }}").collect();
+        assert_eq!(names, vec!["base", "userId", "body"]);
+    }
+
     #[test]
     fn key_split() {
         let key = "headers | content-type";
@@ -1,3 +1,5 @@
+pub mod server;
+
 use super::*;
 use jaq_core::{parse, Ctx, Definitions, Error, RcIter, Val};
 use serde_json::{json, Value};
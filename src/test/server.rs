@@ -0,0 +1,97 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread::JoinHandle,
+};
+
+/// a request the stand-in server received
+#[derive(Debug, Default)]
+pub struct Received {
+    /// the request line and the headers, without line endings
+    pub head: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+impl Received {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.iter().skip(1).find_map(|h| {
+            let (k, v) = h.split_once(':')?;
+            k.trim().eq_ignore_ascii_case(name).then(|| v.trim())
+        })
+    }
+}
+
+/// a response with its `Content-Length`, the connection is closed after it
+pub fn response(status: &str, headers: &[&str], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\n", status);
+    for header in headers {
+        response.push_str(header);
+        response.push_str("\r\n");
+    }
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    response
+}
+
+/// stands in for a server, answering each connection with the next of `responses`
+///
+/// returns the port and the requests received once every response was sent
+pub fn serve(responses: Vec<String>) -> (u16, JoinHandle<Vec<Received>>) {
+    self::serve_with(responses, Ok)
+}
+
+/// `accept` wraps each connection, e.g. in a tls stream, serving stops at the first connection
+/// that fails
+pub fn serve_with<S, F>(responses: Vec<String>, accept: F) -> (u16, JoinHandle<Vec<Received>>)
+where
+    S: Read + Write,
+    F: Fn(TcpStream) -> std::io::Result<S> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = std::thread::spawn(move || {
+        let mut received = Vec::new();
+        for response in responses {
+            let exchange = listener
+                .accept()
+                .and_then(|(socket, _)| accept(socket))
+                .and_then(|stream| self::exchange(stream, &response));
+            match exchange {
+                Ok(request) => received.push(request),
+                Err(_) => break,
+            }
+        }
+        received
+    });
+
+    (port, server)
+}
+
+fn exchange<S: Read + Write>(stream: S, response: &str) -> std::io::Result<Received> {
+    let mut reader = BufReader::new(stream);
+    let mut request = Received::default();
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+            break;
+        }
+        request.head.push(line.trim_end().to_string());
+    }
+
+    let length = request
+        .header("content-length")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+
+    let stream = reader.get_mut();
+    stream.write_all(response.as_bytes())?;
+    stream.flush()?;
+    Ok(request)
+}
@@ -0,0 +1,304 @@
+use crate::error::HatError;
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::BufReader, sync::Arc};
+
+/// ```toml
+/// [client.tls]
+/// ca = ["certs/internal-ca.pem"]
+/// insecure = false
+/// [[client.tls.certificates]]
+/// host = "payments.internal"
+/// cert = "certs/client.pem"
+/// key = "certs/client.key"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM files with certificate authorities trusted in addition to the default roots
+    #[serde(default)]
+    pub ca: Vec<String>,
+    /// client certificates for mutual TLS
+    #[serde(default)]
+    pub certificates: Vec<ClientCertificate>,
+    /// accept any server certificate, only meant for local self-signed stacks
+    #[serde(default)]
+    pub insecure: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// host the certificate is sent to, every host when missing
+    pub host: Option<String>,
+    /// PEM file with the certificate chain
+    pub cert: String,
+    /// PEM file with the private key
+    pub key: String,
+}
+
+impl TlsConfig {
+    /// the certificate for `host`, a certificate for that exact host wins over one without a host
+    pub fn certificate(&self, host: &str) -> Option<&ClientCertificate> {
+        self.certificates
+            .iter()
+            .find(|c| c.host.as_deref() == Some(host))
+            .or_else(|| self.certificates.iter().find(|c| c.host.is_none()))
+    }
+
+    pub fn client_config(
+        &self,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<Arc<rustls::ClientConfig>, HatError> {
+        let builder = rustls::ClientConfig::builder();
+
+        let builder = if self.insecure {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoVerification))
+        } else {
+            let mut roots = RootCertStore::empty();
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+            for path in &self.ca {
+                for cert in self::certs(path)? {
+                    roots
+                        .add(cert)
+                        .map_err(|e| HatError::Tls(format!("{}: {}", path, e)))?;
+                }
+            }
+
+            builder.with_root_certificates(roots)
+        };
+
+        let config = match certificate {
+            Some(c) => builder
+                .with_client_auth_cert(self::certs(&c.cert)?, self::key(&c.key)?)
+                .map_err(|e| HatError::Tls(format!("{}: {}", c.cert, e)))?,
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(Arc::new(config))
+    }
+}
+
+fn certs(path: &str) -> Result<Vec<CertificateDer<'static>>, HatError> {
+    let file = File::open(path).map_err(|e| HatError::Tls(format!("{}: {}", path, e)))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| HatError::Tls(format!("{}: {}", path, e)))?;
+
+    if certs.is_empty() {
+        return Err(HatError::Tls(format!("{} has no certificates", path)));
+    }
+
+    Ok(certs)
+}
+
+fn key(path: &str) -> Result<PrivateKeyDer<'static>, HatError> {
+    let file = File::open(path).map_err(|e| HatError::Tls(format!("{}: {}", path, e)))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| HatError::Tls(format!("{}: {}", path, e)))?
+        .ok_or_else(|| HatError::Tls(format!("{} has no private key", path)))
+}
+
+/// accepts every server certificate for `insecure = true`
+#[derive(Debug)]
+struct NoVerification;
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _: &[u8],
+        _: &CertificateDer<'_>,
+        _: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _: &[u8],
+        _: &CertificateDer<'_>,
+        _: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        rustls::crypto::ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::server;
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+    use rustls::{server::WebPkiClientVerifier, ServerConfig, ServerConnection, StreamOwned};
+    use std::path::PathBuf;
+
+    struct Pki {
+        dir: PathBuf,
+        ca: Certificate,
+    }
+
+    impl Pki {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("hat-tls-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let mut params = CertificateParams::new(vec![]);
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = Certificate::from_params(params).unwrap();
+            std::fs::write(dir.join("ca.pem"), ca.serialize_pem().unwrap()).unwrap();
+
+            Self { dir, ca }
+        }
+
+        /// writes `<name>.pem` and `<name>.key` signed by the CA
+        fn issue(&self, name: &str) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+            let cert =
+                Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()]))
+                    .unwrap();
+            let pem = cert.serialize_pem_with_signer(&self.ca).unwrap();
+            std::fs::write(self.path(&format!("{}.pem", name)), &pem).unwrap();
+            std::fs::write(
+                self.path(&format!("{}.key", name)),
+                cert.serialize_private_key_pem(),
+            )
+            .unwrap();
+
+            (
+                vec![CertificateDer::from(
+                    cert.serialize_der_with_signer(&self.ca).unwrap(),
+                )],
+                PrivateKeyDer::try_from(cert.serialize_private_key_der()).unwrap(),
+            )
+        }
+
+        fn path(&self, file: &str) -> String {
+            self.dir.join(file).to_string_lossy().to_string()
+        }
+
+        /// answers a single request with `200 OK`, requiring a client certificate when `mtls` is set
+        fn serve(&self, mtls: bool) -> u16 {
+            let (certs, key) = self.issue("server");
+            let builder = ServerConfig::builder();
+            let builder = if mtls {
+                let mut roots = RootCertStore::empty();
+                roots
+                    .add(CertificateDer::from(self.ca.serialize_der().unwrap()))
+                    .unwrap();
+                builder.with_client_cert_verifier(
+                    WebPkiClientVerifier::builder(Arc::new(roots))
+                        .build()
+                        .unwrap(),
+                )
+            } else {
+                builder.with_no_client_auth()
+            };
+            let config = Arc::new(builder.with_single_cert(certs, key).unwrap());
+
+            let (port, _) =
+                server::serve_with(vec![server::response("200 OK", &[], "ok")], move |socket| {
+                    let connection =
+                        ServerConnection::new(config.clone()).map_err(std::io::Error::other)?;
+                    Ok(StreamOwned::new(connection, socket))
+                });
+
+            port
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn get(tls: TlsConfig, port: u16) -> Result<String, String> {
+        let certificate = tls.certificate("localhost").cloned();
+        let config = tls
+            .client_config(certificate.as_ref())
+            .map_err(|e| e.to_string())?;
+        let agent = ureq::AgentBuilder::new().tls_config(config).build();
+
+        agent
+            .get(&format!("https://localhost:{}", port))
+            .call()
+            .map_err(|e| e.to_string())?
+            .into_string()
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn private_ca_is_trusted() {
+        let pki = Pki::new("ca");
+
+        let port = pki.serve(false);
+        assert!(get(TlsConfig::default(), port).is_err());
+
+        let port = pki.serve(false);
+        let tls = TlsConfig {
+            ca: vec![pki.path("ca.pem")],
+            ..Default::default()
+        };
+        assert_eq!(get(tls, port).unwrap(), "ok");
+    }
+
+    #[test]
+    fn insecure_accepts_any_certificate() {
+        let pki = Pki::new("insecure");
+        let port = pki.serve(false);
+
+        let tls = TlsConfig {
+            insecure: true,
+            ..Default::default()
+        };
+        assert_eq!(get(tls, port).unwrap(), "ok");
+    }
+
+    #[test]
+    fn client_certificate_is_sent_to_its_host() {
+        let pki = Pki::new("mtls");
+        pki.issue("client");
+
+        let certificate = |host: &str| ClientCertificate {
+            host: Some(host.to_string()),
+            cert: pki.path("client.pem"),
+            key: pki.path("client.key"),
+        };
+
+        let port = pki.serve(true);
+        let tls = TlsConfig {
+            ca: vec![pki.path("ca.pem")],
+            certificates: vec![certificate("example.com")],
+            ..Default::default()
+        };
+        assert!(get(tls, port).is_err());
+
+        let port = pki.serve(true);
+        let tls = TlsConfig {
+            ca: vec![pki.path("ca.pem")],
+            certificates: vec![certificate("localhost")],
+            ..Default::default()
+        };
+        assert_eq!(get(tls, port).unwrap(), "ok");
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::SystemTime;
+
+/// calendar date and time in UTC, times before 1970 are treated as 1970-01-01T00:00:00Z
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Utc {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: u64,
+    pub minute: u64,
+    pub second: u64,
+}
+
+impl From<SystemTime> for Utc {
+    fn from(time: SystemTime) -> Self {
+        let seconds = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+        // days since 1970-01-01 to a civil date, http://howardhinnant.github.io/date_algorithms.html
+        let z = days as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+        Self {
+            year: yoe + era * 400 + i64::from(month <= 2),
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds % 3600 / 60,
+            second: seconds % 60,
+        }
+    }
+}
+
+impl Utc {
+    /// `2015-08-30T12:36:00Z`
+    pub fn iso8601(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn dates_are_converted() {
+        let utc = Utc::from(SystemTime::UNIX_EPOCH + Duration::from_secs(1440938160));
+        assert_eq!(utc.iso8601(), "2015-08-30T12:36:00Z");
+
+        // a leap day
+        let utc = Utc::from(SystemTime::UNIX_EPOCH + Duration::from_secs(951782400));
+        assert_eq!(utc.iso8601(), "2000-02-29T00:00:00Z");
+    }
+}